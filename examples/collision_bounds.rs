@@ -0,0 +1,43 @@
+// How much work the generic collision searches in `cryptopals::collision` take against SHA-256
+// truncated to more and more bits. Every extra 2 bits should roughly double the evaluations, in
+// line with the sqrt(pi / 2 * 2^bits) birthday bound, whatever the search trades for memory.
+//
+// Run with `cargo run --release --example collision_bounds`
+
+use cryptopals::collision::{self, Collision};
+use cryptopals::sha256::SHA256;
+
+const TRIALS: u32 = 5;
+
+fn average_evaluations<F: Fn() -> Collision>(search: F) -> (f64, f64) {
+    let mut total = 0;
+    let mut expected = 0.0;
+
+    for _ in 0..TRIALS {
+        let collision = search();
+        total += collision.evaluations;
+        expected = collision.expected_evaluations();
+    }
+
+    (total as f64 / TRIALS as f64, expected)
+}
+
+fn main() {
+    println!(
+        "{:>4}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}",
+        "bits", "expected", "birthday", "floyd", "brent", "dist. pts"
+    );
+
+    for bits in (16..=32).step_by(4) {
+        let (birthday, expected) = average_evaluations(|| collision::birthday::<SHA256>(bits));
+        let (floyd, _) = average_evaluations(|| collision::floyd::<SHA256>(bits));
+        let (brent, _) = average_evaluations(|| collision::brent::<SHA256>(bits));
+        let (distinguished, _) =
+            average_evaluations(|| collision::distinguished_points::<SHA256>(bits, bits / 4));
+
+        println!(
+            "{:>4}  {:>10.0}  {:>10.0}  {:>10.0}  {:>10.0}  {:>10.0}",
+            bits, expected, birthday, floyd, brent, distinguished
+        );
+    }
+}
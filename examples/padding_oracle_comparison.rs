@@ -0,0 +1,146 @@
+// The CBC padding oracle attack from challenge 17, run against every padding scheme in
+// `cryptopals::padding`. The attack only needs some padding pattern of each length that the
+// server accepts, so it works on PKCS#7, ANSI X.923 and ISO 7816-4 alike. ISO 10126 only checks
+// the length byte, so any of several guesses are accepted and the oracle only narrows bytes down
+// to a range, and zero padding can't be rejected at all.
+//
+// Run with `cargo run --example padding_oracle_comparison`
+
+use rand::prelude::*;
+
+use cryptopals::aes::Aes128;
+use cryptopals::modes::Cbc;
+use cryptopals::padding::{AnsiX923, Iso10126, Iso7816, Padding, Pkcs7, ZeroPadding};
+
+const BLOCK_SIZE: usize = 16;
+const SECRET: &[u8] = b"Ice Ice Baby, the padding oracle is back with a brand new invention";
+
+struct Server<P> {
+    cbc: Cbc<Aes128, P>,
+    queries: usize,
+}
+
+impl<P: Padding> Server<P> {
+    fn new(padding: P) -> Self {
+        let key: [u8; 16] = thread_rng().gen();
+
+        Self {
+            cbc: Cbc::with_padding(Aes128::new(&key), padding),
+            queries: 0,
+        }
+    }
+
+    fn is_valid(&mut self, iv: &[u8], ciphertext: &[u8]) -> bool {
+        self.queries += 1;
+        self.cbc.decrypt(ciphertext, iv).is_ok()
+    }
+}
+
+enum Outcome {
+    Recovered(Vec<u8>),
+    // The block's intermediate bytes that were recovered before the oracle stopped being useful
+    Ambiguous(Vec<u8>),
+}
+
+fn main() {
+    println!(
+        "{:<12} {:>10} {:>10} {:>10}",
+        "scheme", "recovered", "correct", "queries"
+    );
+
+    compare("PKCS#7", Pkcs7);
+    compare("ANSI X.923", AnsiX923);
+    compare("ISO 10126", Iso10126);
+    compare("ISO 7816-4", Iso7816);
+    compare("zero", ZeroPadding);
+}
+
+fn compare<P: Padding>(name: &str, padding: P) {
+    let mut server = Server::new(padding);
+    let iv: [u8; BLOCK_SIZE] = thread_rng().gen();
+    let ciphertext = server.cbc.encrypt(SECRET, &iv);
+    let padded = server.cbc.decrypt_blocks(&ciphertext, &iv);
+
+    let mut recovered = Vec::new();
+    let mut previous_block = &iv[..];
+
+    for block in ciphertext.chunks(BLOCK_SIZE) {
+        let intermediate = match attack_block(&mut server, block) {
+            Outcome::Recovered(intermediate) => intermediate,
+            Outcome::Ambiguous(partial) => partial,
+        };
+
+        // Only the trailing bytes get recovered when the attack gives up partway through a block
+        let offset = BLOCK_SIZE - intermediate.len();
+        recovered.extend(vec![None; offset]);
+        recovered.extend(
+            intermediate
+                .iter()
+                .zip(&previous_block[offset..])
+                .map(|(byte, previous)| Some(byte ^ previous)),
+        );
+
+        previous_block = block;
+    }
+
+    let recovered_count = recovered.iter().flatten().count();
+    let correct_count = recovered
+        .iter()
+        .zip(&padded)
+        .filter(|&(&byte, &actual)| byte == Some(actual))
+        .count();
+
+    println!(
+        "{:<12} {:>10} {:>10} {:>10}",
+        name,
+        format!("{}/{}", recovered_count, padded.len()),
+        correct_count,
+        server.queries
+    );
+}
+
+// Recovers the block cipher output for `block`, from the last byte backwards. For each length the
+// IV is chosen so the decrypted block ends in what the scheme would pad a block with that many
+// bytes of padding with
+fn attack_block<P: Padding>(server: &mut Server<P>, block: &[u8]) -> Outcome {
+    let mut intermediate = vec![0; BLOCK_SIZE];
+
+    for pad_size in 1..=BLOCK_SIZE {
+        let position = BLOCK_SIZE - pad_size;
+        let pattern = server.cbc.padding().pad(&vec![0; position], BLOCK_SIZE);
+
+        let mut iv = vec![0; BLOCK_SIZE];
+        for index in position + 1..BLOCK_SIZE {
+            iv[index] = intermediate[index] ^ pattern[index];
+        }
+
+        let mut candidates = Vec::new();
+        for guess in 0..=255 {
+            iv[position] = guess;
+
+            if !server.is_valid(&iv, block) {
+                continue;
+            }
+
+            // Make sure the padding didn't just happen to be longer than the one we aimed for
+            if position > 0 {
+                iv[position - 1] ^= 1;
+                let still_valid = server.is_valid(&iv, block);
+                iv[position - 1] ^= 1;
+
+                if !still_valid {
+                    continue;
+                }
+            }
+
+            candidates.push(guess);
+        }
+
+        match candidates[..] {
+            [guess] => intermediate[position] = guess ^ pattern[position],
+            _ => return Outcome::Ambiguous(intermediate[position + 1..].to_vec()),
+        }
+    }
+
+    Outcome::Recovered(intermediate)
+}
@@ -0,0 +1,65 @@
+// XTS has no integrity protection, and since every 16-byte block within a sector is encrypted
+// independently, an attacker who saw an old version of a sector can roll back individual blocks
+// of its current version. Moving blocks around doesn't work (the tweak depends on the position),
+// and flipping bits garbles the whole block instead of flipping the same plaintext bits.
+//
+// Run with `cargo run --example xts_malleability`
+
+use cryptopals::aes::Aes128;
+use cryptopals::modes::Xts;
+
+const SECTOR_NUMBER: u128 = 42;
+const BLOCK_SIZE: usize = 16;
+
+fn main() {
+    let xts = Xts::new(
+        Aes128::new(b"YELLOW SUBMARINE"),
+        Aes128::new(b"PURPLE SUBMARINE"),
+    );
+
+    // Each record takes exactly one block
+    let mut old_sector = sector(&["user: alice     ", "balance: 000100 ", "status: locked  "]);
+    xts.encrypt_sector(&mut old_sector, SECTOR_NUMBER);
+
+    let mut current_sector = sector(&["user: alice     ", "balance: 000002 ", "status: active  "]);
+    xts.encrypt_sector(&mut current_sector, SECTOR_NUMBER);
+
+    println!("Current sector:");
+    show(&xts, &current_sector);
+
+    // Replaying the old balance block while keeping the new status decrypts cleanly
+    let mut replayed = current_sector.clone();
+    replace_block(&mut replayed, &old_sector, 1, 1);
+    println!("\nAfter replaying the old balance block:");
+    show(&xts, &replayed);
+
+    // The same ciphertext block at a different position decrypts to garbage
+    let mut moved = current_sector.clone();
+    replace_block(&mut moved, &old_sector, 1, 2);
+    println!("\nAfter copying the old balance block over the status block:");
+    show(&xts, &moved);
+
+    // A single flipped bit scrambles its whole block (and nothing else)
+    let mut flipped = current_sector;
+    flipped[BLOCK_SIZE + 15] ^= 1;
+    println!("\nAfter flipping one bit of the balance block:");
+    show(&xts, &flipped);
+}
+
+fn sector(records: &[&str]) -> Vec<u8> {
+    records.iter().flat_map(|record| record.bytes()).collect()
+}
+
+fn replace_block(target: &mut [u8], source: &[u8], source_index: usize, target_index: usize) {
+    let source_block = &source[source_index * BLOCK_SIZE..][..BLOCK_SIZE];
+    target[target_index * BLOCK_SIZE..][..BLOCK_SIZE].copy_from_slice(source_block);
+}
+
+fn show(xts: &Xts<Aes128>, ciphertext: &[u8]) {
+    let mut plaintext = ciphertext.to_vec();
+    xts.decrypt_sector(&mut plaintext, SECTOR_NUMBER);
+
+    for block in plaintext.chunks(BLOCK_SIZE) {
+        println!("  {:?}", String::from_utf8_lossy(block));
+    }
+}
@@ -7,8 +7,8 @@ use std::sync::{Arc, Once};
 use std::time::Duration;
 
 use cryptopals::{
-    encoding::{bytes_to_hex, hex_to_bytes},
-    hmac,
+    encoding::{bytes_to_hex, try_hex_to_bytes},
+    hmac::{self, HmacSha1},
 };
 
 #[derive(Deserialize)]
@@ -20,7 +20,9 @@ struct Query {
 
 #[tokio::main]
 async fn main() {
-    let oracle = Arc::new(Oracle::new());
+    // The leaky comparison is the whole point of challenges 31 and 32, so it's the default
+    let constant_time = std::env::args().any(|arg| arg == "--constant-time");
+    let oracle = Arc::new(Oracle::new(constant_time));
 
     let test = warp::path!("test")
         .and(warp::query::<Query>())
@@ -31,19 +33,26 @@ async fn main() {
 
 struct Oracle {
     key: Vec<u8>,
+    constant_time: bool,
 }
 
 static PRINT_SIGNATURE: Once = Once::new();
 
 impl Oracle {
-    fn new() -> Self {
+    fn new(constant_time: bool) -> Self {
         use rand::{distributions::Standard, thread_rng, Rng};
 
         let key = thread_rng().sample_iter(Standard).take(128).collect();
-        Self { key }
+        Self { key, constant_time }
     }
 
     async fn validate(&self, message: &[u8], signature: &[u8], delay_millis: u64) -> bool {
+        if self.constant_time {
+            let mut hmac = HmacSha1::new(&self.key);
+            hmac.update(message);
+            return hmac.verify(signature);
+        }
+
         let computed_signature = hmac::hmac_sha1(&self.key, message);
 
         PRINT_SIGNATURE.call_once(|| {
@@ -64,10 +73,13 @@ impl Oracle {
 
 async fn validate_query(oracle: Arc<Oracle>, query: Query) -> Result<impl warp::Reply, Infallible> {
     let message = query.filename.as_bytes();
-    let signature = &hex_to_bytes(&query.signature);
+    let signature = match try_hex_to_bytes(&query.signature) {
+        Ok(signature) => signature,
+        Err(_) => return Ok(StatusCode::BAD_REQUEST),
+    };
     let delay = query.delay.unwrap_or(50);
 
-    let result = oracle.validate(message, signature, delay).await;
+    let result = oracle.validate(message, &signature, delay).await;
 
     if result {
         Ok(StatusCode::OK)
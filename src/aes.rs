@@ -1,104 +1,113 @@
-use super::padding::{pkcs7_pad, pkcs7_unpad};
-use super::xor::fixed_xor;
+pub mod block;
 
-const BLOCK_SIZE: usize = 16;
-
-pub fn aes_128_ecb_encrypt(plaintext: &[u8], key: &[u8], autopad: bool) -> Vec<u8> {
-    use openssl::symm::{Cipher, Crypter, Mode};
-    let cipher = Cipher::aes_128_ecb();
+use crate::block_cipher::BlockCipher;
+use crate::error::{or_panic, Error, Result};
+use crate::modes::{Cbc, Ctr, Ecb};
 
-    let mut ciphertext = vec![0; plaintext.len() + cipher.block_size()];
-    let mut crypter = Crypter::new(cipher, Mode::Encrypt, key, None).unwrap();
-    crypter.pad(autopad);
+use self::block::KeySchedule;
 
-    let count = crypter.update(plaintext, &mut ciphertext).unwrap();
-    let rest = crypter.finalize(&mut ciphertext[count..]).unwrap();
+const BLOCK_SIZE: usize = 16;
 
-    ciphertext.truncate(count + rest);
-    ciphertext
+macro_rules! aes_variant {
+    ($name:ident, $key_size:expr) => {
+        pub struct $name(KeySchedule);
+
+        impl $name {
+            pub const KEY_SIZE: usize = $key_size;
+
+            pub fn new(key: &[u8]) -> Self {
+                Self::try_new(key)
+                    .unwrap_or_else(|err| panic!("Invalid {} key: {}", stringify!($name), err))
+            }
+
+            pub fn try_new(key: &[u8]) -> Result<Self> {
+                if key.len() != Self::KEY_SIZE {
+                    return Err(Error::InvalidLength {
+                        expected: Self::KEY_SIZE,
+                        actual: key.len(),
+                    });
+                }
+
+                Ok($name(KeySchedule::new(key)))
+            }
+
+            pub fn key_schedule(&self) -> &KeySchedule {
+                &self.0
+            }
+        }
+
+        impl BlockCipher for $name {
+            const BLOCK_SIZE: usize = BLOCK_SIZE;
+
+            fn encrypt_block(&self, block: &mut [u8]) {
+                self.0.encrypt_block(block);
+            }
+
+            fn decrypt_block(&self, block: &mut [u8]) {
+                self.0.decrypt_block(block);
+            }
+        }
+    };
 }
 
-pub fn aes_128_ecb_decrypt(ciphertext: &[u8], key: &[u8], autopad: bool) -> Vec<u8> {
-    use openssl::symm::{Cipher, Crypter, Mode};
-    let cipher = Cipher::aes_128_ecb();
-
-    let mut plaintext = vec![0; ciphertext.len() + cipher.block_size()];
-    let mut decrypter = Crypter::new(cipher, Mode::Decrypt, key, None).unwrap();
-    decrypter.pad(autopad);
-
-    let count = decrypter.update(ciphertext, &mut plaintext).unwrap();
-    let rest = decrypter.finalize(&mut plaintext[count..]).unwrap();
+aes_variant!(Aes128, 16);
+aes_variant!(Aes192, 24);
+aes_variant!(Aes256, 32);
 
-    plaintext.truncate(count + rest);
-    plaintext
+pub fn aes_128_ecb_encrypt(plaintext: &[u8], key: &[u8], autopad: bool) -> Vec<u8> {
+    or_panic(try_aes_128_ecb_encrypt(plaintext, key, autopad))
 }
 
-pub fn aes_128_cbc_encrypt(plaintext: &[u8], key: &[u8], iv: &[u8]) -> Vec<u8> {
-    let mut previous_block = iv.to_vec();
-    let padded_plaintext = pkcs7_pad(plaintext, BLOCK_SIZE);
+pub fn try_aes_128_ecb_encrypt(plaintext: &[u8], key: &[u8], autopad: bool) -> Result<Vec<u8>> {
+    let ecb = Ecb::new(Aes128::try_new(key)?);
 
-    let mut result = Vec::with_capacity(padded_plaintext.len());
+    if autopad {
+        Ok(ecb.encrypt(plaintext))
+    } else {
+        ecb.try_encrypt_blocks(plaintext)
+    }
+}
 
-    for block_start in (0..padded_plaintext.len()).step_by(BLOCK_SIZE) {
-        let block_end = block_start + BLOCK_SIZE;
-        let block_size = block_end - block_start;
+pub fn aes_128_ecb_decrypt(ciphertext: &[u8], key: &[u8], autopad: bool) -> Vec<u8> {
+    or_panic(try_aes_128_ecb_decrypt(ciphertext, key, autopad))
+}
 
-        let input_plaintext = fixed_xor(
-            &previous_block[0..block_size],
-            &padded_plaintext[block_start..block_end],
-        );
-        let new_block = aes_128_ecb_encrypt(&input_plaintext, key, false);
-        result.extend_from_slice(&new_block);
+pub fn try_aes_128_ecb_decrypt(ciphertext: &[u8], key: &[u8], autopad: bool) -> Result<Vec<u8>> {
+    let ecb = Ecb::new(Aes128::try_new(key)?);
 
-        previous_block = new_block;
+    if autopad {
+        ecb.decrypt(ciphertext)
+    } else {
+        ecb.try_decrypt_blocks(ciphertext)
     }
+}
 
-    result
+pub fn aes_128_cbc_encrypt(plaintext: &[u8], key: &[u8], iv: &[u8]) -> Vec<u8> {
+    or_panic(try_aes_128_cbc_encrypt(plaintext, key, iv))
 }
 
-pub fn aes_128_cbc_decrypt(
-    ciphertext: &[u8],
-    key: &[u8],
-    iv: &[u8],
-) -> Result<Vec<u8>, &'static str> {
-    let mut previous_block = iv;
-    let ecb_decrypted = aes_128_ecb_decrypt(ciphertext, key, false);
-    let mut result = Vec::with_capacity(ecb_decrypted.len());
-
-    for block_start in (0..ecb_decrypted.len()).step_by(BLOCK_SIZE) {
-        let block_end = (block_start + BLOCK_SIZE).min(ecb_decrypted.len());
-        let block_size = block_end - block_start;
-
-        let new_block = fixed_xor(
-            &previous_block[0..block_size],
-            &ecb_decrypted[block_start..block_end],
-        );
-        result.extend_from_slice(&new_block);
-
-        previous_block = &ciphertext[block_start..block_end];
-    }
+pub fn try_aes_128_cbc_encrypt(plaintext: &[u8], key: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+    Cbc::new(Aes128::try_new(key)?).try_encrypt(plaintext, iv)
+}
 
-    pkcs7_unpad(&result)
+pub fn aes_128_cbc_decrypt(ciphertext: &[u8], key: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+    Cbc::new(Aes128::try_new(key)?).decrypt(ciphertext, iv)
 }
 
 pub fn aes_128_ctr_encrypt(plaintext: &[u8], key: &[u8], nonce: &[u8]) -> Vec<u8> {
-    let keystream = aes_128_ctr_keystream(key, nonce);
+    or_panic(try_aes_128_ctr_encrypt(plaintext, key, nonce))
+}
 
-    plaintext
-        .iter()
-        .zip(keystream)
-        .map(|(plaintext_byte, keystream_byte)| plaintext_byte ^ keystream_byte)
-        .collect()
+pub fn try_aes_128_ctr_encrypt(plaintext: &[u8], key: &[u8], nonce: &[u8]) -> Result<Vec<u8>> {
+    Ctr::new(Aes128::try_new(key)?).try_encrypt(plaintext, nonce)
 }
 
 pub fn aes_128_ctr_decrypt(ciphertext: &[u8], key: &[u8], nonce: &[u8]) -> Vec<u8> {
-    let keystream = aes_128_ctr_keystream(key, nonce);
+    or_panic(try_aes_128_ctr_decrypt(ciphertext, key, nonce))
+}
 
-    ciphertext
-        .iter()
-        .zip(keystream)
-        .map(|(ciphertext_byte, keystream_byte)| ciphertext_byte ^ keystream_byte)
-        .collect()
+pub fn try_aes_128_ctr_decrypt(ciphertext: &[u8], key: &[u8], nonce: &[u8]) -> Result<Vec<u8>> {
+    Ctr::new(Aes128::try_new(key)?).try_decrypt(ciphertext, nonce)
 }
 
 pub fn aes_128_ctr_edit(
@@ -108,30 +117,142 @@ pub fn aes_128_ctr_edit(
     offset: usize,
     new_plaintext: &[u8],
 ) -> Vec<u8> {
-    if new_plaintext.len() + offset > original_ciphertext.len() {
-        panic!("Can't edit beyond the existing ciphertext");
-    }
+    or_panic(try_aes_128_ctr_edit(
+        original_ciphertext,
+        key,
+        nonce,
+        offset,
+        new_plaintext,
+    ))
+}
+
+pub fn try_aes_128_ctr_edit(
+    original_ciphertext: &[u8],
+    key: &[u8],
+    nonce: &[u8],
+    offset: usize,
+    new_plaintext: &[u8],
+) -> Result<Vec<u8>> {
+    Ctr::new(Aes128::try_new(key)?).try_edit(original_ciphertext, nonce, offset, new_plaintext)
+}
 
-    let keystream = aes_128_ctr_keystream(key, nonce).skip(offset);
-    let new_ciphertext = keystream.zip(new_plaintext).map(|(a, b)| a ^ b);
+#[cfg(test)]
+use crate::encoding::{bytes_to_hex, hex_to_bytes};
 
-    let mut result = original_ciphertext.to_vec();
+#[cfg(test)]
+const NIST_KEY_128: &str = "2b7e151628aed2a6abf7158809cf4f3c";
 
-    for (index, byte) in new_ciphertext.enumerate() {
-        result[index + offset] = byte;
-    }
+#[cfg(test)]
+const NIST_PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51";
 
-    result
+#[test]
+fn test_aes_128_ecb_nist_vector() {
+    let key = hex_to_bytes(NIST_KEY_128);
+    let plaintext = hex_to_bytes(NIST_PLAINTEXT);
+
+    let ciphertext = aes_128_ecb_encrypt(&plaintext, &key, false);
+    assert_eq!(
+        bytes_to_hex(&ciphertext),
+        "3ad77bb40d7a3660a89ecaf32466ef97f5d3d58503b9699de785895a96fdbaaf"
+    );
+
+    assert_eq!(aes_128_ecb_decrypt(&ciphertext, &key, false), plaintext);
 }
 
-fn aes_128_ctr_keystream<'k>(key: &'k [u8], nonce: &'k [u8]) -> impl Iterator<Item = u8> + 'k {
-    let mut block = [0; BLOCK_SIZE];
-    (&mut block[..BLOCK_SIZE / 2]).copy_from_slice(nonce);
+#[test]
+fn test_aes_128_ecb_autopad_roundtrip() {
+    let key = b"YELLOW SUBMARINE";
+    let ciphertext = aes_128_ecb_encrypt(b"not block aligned", key, true);
 
-    (0u64..).flat_map(move |block_counter| {
-        let counter_bytes = block_counter.to_le_bytes();
-        (&mut block[BLOCK_SIZE / 2..]).copy_from_slice(&counter_bytes);
+    assert_eq!(ciphertext.len(), 32);
+    assert_eq!(
+        aes_128_ecb_decrypt(&ciphertext, key, true),
+        b"not block aligned"
+    );
+}
+
+#[test]
+fn test_aes_cbc_nist_vectors() {
+    let iv = hex_to_bytes("000102030405060708090a0b0c0d0e0f");
+    let plaintext = hex_to_bytes(NIST_PLAINTEXT);
+
+    let aes_128 = Cbc::new(Aes128::new(&hex_to_bytes(NIST_KEY_128)));
+    let ciphertext = aes_128.encrypt_blocks(&plaintext, &iv);
+    assert_eq!(
+        bytes_to_hex(&ciphertext),
+        "7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b2"
+    );
+    assert_eq!(aes_128.decrypt_blocks(&ciphertext, &iv), plaintext);
+
+    let aes_192 = Cbc::new(Aes192::new(&hex_to_bytes(
+        "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+    )));
+    assert_eq!(
+        bytes_to_hex(&aes_192.encrypt_blocks(&plaintext, &iv)),
+        "4f021db243bc633d7178183a9fa071e8b4d9ada9ad7dedf4e5e738763f69145a"
+    );
+
+    let aes_256 = Cbc::new(Aes256::new(&hex_to_bytes(
+        "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+    )));
+    assert_eq!(
+        bytes_to_hex(&aes_256.encrypt_blocks(&plaintext, &iv)),
+        "f58c4c04d6e5f1ba779eabfb5f7bfbd69cfc4e967edb808d679f777bc6702c7d"
+    );
+}
+
+#[test]
+fn test_aes_128_ctr_challenge_18() {
+    use crate::encoding::base64_to_bytes;
+
+    let ciphertext =
+        base64_to_bytes("L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==");
+    let plaintext = aes_128_ctr_decrypt(&ciphertext, b"YELLOW SUBMARINE", &[0; 8]);
+
+    assert_eq!(
+        String::from_utf8(plaintext).unwrap(),
+        "Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby "
+    );
+}
 
-        aes_128_ecb_encrypt(&block, key, false)
-    })
+#[test]
+fn test_aes_128_rejects_malformed_input() {
+    let key = b"YELLOW SUBMARINE";
+
+    assert_eq!(
+        try_aes_128_ecb_encrypt(b"data", b"short key", true),
+        Err(Error::InvalidLength {
+            expected: 16,
+            actual: 9
+        })
+    );
+    assert_eq!(
+        try_aes_128_ecb_decrypt(&[0; 17], key, false),
+        Err(Error::NotBlockAligned {
+            block_size: 16,
+            length: 17
+        })
+    );
+    assert_eq!(
+        aes_128_cbc_decrypt(&[0; 16], key, &[0; 8]),
+        Err(Error::InvalidLength {
+            expected: 16,
+            actual: 8
+        })
+    );
+    assert_eq!(
+        aes_128_cbc_decrypt(
+            &Cbc::new(Aes128::new(key)).encrypt_blocks(&[0; 32], &[0; 16]),
+            key,
+            &[0; 16]
+        ),
+        Err(Error::BadPadding)
+    );
+    assert_eq!(
+        try_aes_128_ctr_edit(&[0; 10], key, &[0; 8], 8, b"abc"),
+        Err(Error::InvalidLength {
+            expected: 2,
+            actual: 3
+        })
+    );
 }
@@ -0,0 +1,323 @@
+use std::convert::TryInto;
+
+pub const BLOCK_SIZE: usize = 16;
+
+const SBOX: [u8; 256] = [
+    0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+    0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+    0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+    0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+    0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+    0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+    0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+    0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+    0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+    0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+    0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+    0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+    0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+    0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+    0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+    0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+];
+
+const INV_SBOX: [u8; 256] = [
+    0x52, 0x09, 0x6a, 0xd5, 0x30, 0x36, 0xa5, 0x38, 0xbf, 0x40, 0xa3, 0x9e, 0x81, 0xf3, 0xd7, 0xfb,
+    0x7c, 0xe3, 0x39, 0x82, 0x9b, 0x2f, 0xff, 0x87, 0x34, 0x8e, 0x43, 0x44, 0xc4, 0xde, 0xe9, 0xcb,
+    0x54, 0x7b, 0x94, 0x32, 0xa6, 0xc2, 0x23, 0x3d, 0xee, 0x4c, 0x95, 0x0b, 0x42, 0xfa, 0xc3, 0x4e,
+    0x08, 0x2e, 0xa1, 0x66, 0x28, 0xd9, 0x24, 0xb2, 0x76, 0x5b, 0xa2, 0x49, 0x6d, 0x8b, 0xd1, 0x25,
+    0x72, 0xf8, 0xf6, 0x64, 0x86, 0x68, 0x98, 0x16, 0xd4, 0xa4, 0x5c, 0xcc, 0x5d, 0x65, 0xb6, 0x92,
+    0x6c, 0x70, 0x48, 0x50, 0xfd, 0xed, 0xb9, 0xda, 0x5e, 0x15, 0x46, 0x57, 0xa7, 0x8d, 0x9d, 0x84,
+    0x90, 0xd8, 0xab, 0x00, 0x8c, 0xbc, 0xd3, 0x0a, 0xf7, 0xe4, 0x58, 0x05, 0xb8, 0xb3, 0x45, 0x06,
+    0xd0, 0x2c, 0x1e, 0x8f, 0xca, 0x3f, 0x0f, 0x02, 0xc1, 0xaf, 0xbd, 0x03, 0x01, 0x13, 0x8a, 0x6b,
+    0x3a, 0x91, 0x11, 0x41, 0x4f, 0x67, 0xdc, 0xea, 0x97, 0xf2, 0xcf, 0xce, 0xf0, 0xb4, 0xe6, 0x73,
+    0x96, 0xac, 0x74, 0x22, 0xe7, 0xad, 0x35, 0x85, 0xe2, 0xf9, 0x37, 0xe8, 0x1c, 0x75, 0xdf, 0x6e,
+    0x47, 0xf1, 0x1a, 0x71, 0x1d, 0x29, 0xc5, 0x89, 0x6f, 0xb7, 0x62, 0x0e, 0xaa, 0x18, 0xbe, 0x1b,
+    0xfc, 0x56, 0x3e, 0x4b, 0xc6, 0xd2, 0x79, 0x20, 0x9a, 0xdb, 0xc0, 0xfe, 0x78, 0xcd, 0x5a, 0xf4,
+    0x1f, 0xdd, 0xa8, 0x33, 0x88, 0x07, 0xc7, 0x31, 0xb1, 0x12, 0x10, 0x59, 0x27, 0x80, 0xec, 0x5f,
+    0x60, 0x51, 0x7f, 0xa9, 0x19, 0xb5, 0x4a, 0x0d, 0x2d, 0xe5, 0x7a, 0x9f, 0x93, 0xc9, 0x9c, 0xef,
+    0xa0, 0xe0, 0x3b, 0x4d, 0xae, 0x2a, 0xf5, 0xb0, 0xc8, 0xeb, 0xbb, 0x3c, 0x83, 0x53, 0x99, 0x61,
+    0x17, 0x2b, 0x04, 0x7e, 0xba, 0x77, 0xd6, 0x26, 0xe1, 0x69, 0x14, 0x63, 0x55, 0x21, 0x0c, 0x7d,
+];
+
+pub struct KeySchedule {
+    round_keys: Vec<[u8; BLOCK_SIZE]>,
+}
+
+impl KeySchedule {
+    pub fn new(key: &[u8]) -> Self {
+        Self::with_rounds(key, key.len() / 4 + 6)
+    }
+
+    // Reduced (or extended) round variants use the same key expansion, just stopping early (or
+    // running it for longer); the last round always skips MixColumns, as in the full cipher
+    pub fn with_rounds(key: &[u8], rounds: usize) -> Self {
+        if rounds == 0 {
+            panic!("AES needs at least one round");
+        }
+
+        let round_keys = expand_key(key, rounds)
+            .chunks(4)
+            .map(|words| {
+                let mut round_key = [0; BLOCK_SIZE];
+                for (target, word) in round_key.chunks_mut(4).zip(words) {
+                    target.copy_from_slice(word);
+                }
+                round_key
+            })
+            .collect();
+
+        Self { round_keys }
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.round_keys.len() - 1
+    }
+
+    pub fn round_keys(&self) -> &[[u8; BLOCK_SIZE]] {
+        &self.round_keys
+    }
+
+    pub fn encrypt_block(&self, block: &mut [u8]) {
+        let state = as_state(block);
+        let last_round = self.rounds();
+
+        add_round_key(state, &self.round_keys[0]);
+
+        for round in 1..last_round {
+            sub_bytes(state);
+            shift_rows(state);
+            mix_columns(state);
+            add_round_key(state, &self.round_keys[round]);
+        }
+
+        sub_bytes(state);
+        shift_rows(state);
+        add_round_key(state, &self.round_keys[last_round]);
+    }
+
+    pub fn decrypt_block(&self, block: &mut [u8]) {
+        let state = as_state(block);
+        let last_round = self.rounds();
+
+        add_round_key(state, &self.round_keys[last_round]);
+        inv_shift_rows(state);
+        inv_sub_bytes(state);
+
+        for round in (1..last_round).rev() {
+            add_round_key(state, &self.round_keys[round]);
+            inv_mix_columns(state);
+            inv_shift_rows(state);
+            inv_sub_bytes(state);
+        }
+
+        add_round_key(state, &self.round_keys[0]);
+    }
+}
+
+fn as_state(block: &mut [u8]) -> &mut [u8; BLOCK_SIZE] {
+    let block_len = block.len();
+
+    block.try_into().unwrap_or_else(|_| {
+        panic!(
+            "AES blocks must have {} bytes (got {})",
+            BLOCK_SIZE, block_len
+        )
+    })
+}
+
+fn expand_key(key: &[u8], rounds: usize) -> Vec<[u8; 4]> {
+    if ![16, 24, 32].contains(&key.len()) {
+        panic!("Invalid AES key length: {}", key.len());
+    }
+
+    let key_words = key.len() / 4;
+
+    let total_words = 4 * (rounds + 1);
+    let mut words: Vec<[u8; 4]> = key.chunks(4).map(|word| word.try_into().unwrap()).collect();
+
+    let mut rcon = 0x01;
+
+    for i in key_words..total_words {
+        let mut temp = words[i - 1];
+
+        if i % key_words == 0 {
+            temp.rotate_left(1);
+            sub_word(&mut temp);
+            temp[0] ^= rcon;
+            rcon = xtime(rcon);
+        } else if key_words > 6 && i % key_words == 4 {
+            sub_word(&mut temp);
+        }
+
+        let previous = words[i - key_words];
+        for (byte, previous_byte) in temp.iter_mut().zip(&previous) {
+            *byte ^= previous_byte;
+        }
+
+        words.push(temp);
+    }
+
+    words.truncate(total_words);
+    words
+}
+
+fn sub_word(word: &mut [u8; 4]) {
+    for byte in word.iter_mut() {
+        *byte = SBOX[*byte as usize];
+    }
+}
+
+// The state is kept in the same column-major order as the input block, so the byte at row `r`
+// and column `c` lives at index `r + 4 * c`
+pub fn add_round_key(state: &mut [u8; BLOCK_SIZE], round_key: &[u8; BLOCK_SIZE]) {
+    for (byte, key_byte) in state.iter_mut().zip(round_key) {
+        *byte ^= key_byte;
+    }
+}
+
+pub fn sub_bytes(state: &mut [u8; BLOCK_SIZE]) {
+    for byte in state.iter_mut() {
+        *byte = SBOX[*byte as usize];
+    }
+}
+
+pub fn inv_sub_bytes(state: &mut [u8; BLOCK_SIZE]) {
+    for byte in state.iter_mut() {
+        *byte = INV_SBOX[*byte as usize];
+    }
+}
+
+pub fn shift_rows(state: &mut [u8; BLOCK_SIZE]) {
+    let original = *state;
+
+    for row in 1..4 {
+        for column in 0..4 {
+            state[row + 4 * column] = original[row + 4 * ((column + row) % 4)];
+        }
+    }
+}
+
+pub fn inv_shift_rows(state: &mut [u8; BLOCK_SIZE]) {
+    let original = *state;
+
+    for row in 1..4 {
+        for column in 0..4 {
+            state[row + 4 * ((column + row) % 4)] = original[row + 4 * column];
+        }
+    }
+}
+
+pub fn mix_columns(state: &mut [u8; BLOCK_SIZE]) {
+    for column in state.chunks_mut(4) {
+        let [a0, a1, a2, a3] = [column[0], column[1], column[2], column[3]];
+
+        column[0] = gmul(a0, 2) ^ gmul(a1, 3) ^ a2 ^ a3;
+        column[1] = a0 ^ gmul(a1, 2) ^ gmul(a2, 3) ^ a3;
+        column[2] = a0 ^ a1 ^ gmul(a2, 2) ^ gmul(a3, 3);
+        column[3] = gmul(a0, 3) ^ a1 ^ a2 ^ gmul(a3, 2);
+    }
+}
+
+pub fn inv_mix_columns(state: &mut [u8; BLOCK_SIZE]) {
+    for column in state.chunks_mut(4) {
+        let [a0, a1, a2, a3] = [column[0], column[1], column[2], column[3]];
+
+        column[0] = gmul(a0, 14) ^ gmul(a1, 11) ^ gmul(a2, 13) ^ gmul(a3, 9);
+        column[1] = gmul(a0, 9) ^ gmul(a1, 14) ^ gmul(a2, 11) ^ gmul(a3, 13);
+        column[2] = gmul(a0, 13) ^ gmul(a1, 9) ^ gmul(a2, 14) ^ gmul(a3, 11);
+        column[3] = gmul(a0, 11) ^ gmul(a1, 13) ^ gmul(a2, 9) ^ gmul(a3, 14);
+    }
+}
+
+fn xtime(byte: u8) -> u8 {
+    let shifted = byte << 1;
+
+    if byte & 0x80 != 0 {
+        shifted ^ 0x1b
+    } else {
+        shifted
+    }
+}
+
+fn gmul(mut a: u8, mut b: u8) -> u8 {
+    let mut result = 0;
+
+    while b > 0 {
+        if b & 1 != 0 {
+            result ^= a;
+        }
+
+        a = xtime(a);
+        b >>= 1;
+    }
+
+    result
+}
+
+#[cfg(test)]
+use crate::encoding::{bytes_to_hex, hex_to_bytes};
+
+#[cfg(test)]
+fn check_fips_197_vector(key_hex: &str, expected_ciphertext_hex: &str) {
+    let schedule = KeySchedule::new(&hex_to_bytes(key_hex));
+
+    let mut block = hex_to_bytes("00112233445566778899aabbccddeeff");
+    schedule.encrypt_block(&mut block);
+    assert_eq!(bytes_to_hex(&block), expected_ciphertext_hex);
+
+    schedule.decrypt_block(&mut block);
+    assert_eq!(bytes_to_hex(&block), "00112233445566778899aabbccddeeff");
+}
+
+#[test]
+fn test_fips_197_aes_128() {
+    check_fips_197_vector(
+        "000102030405060708090a0b0c0d0e0f",
+        "69c4e0d86a7b0430d8cdb78070b4c55a",
+    );
+}
+
+#[test]
+fn test_fips_197_aes_192() {
+    check_fips_197_vector(
+        "000102030405060708090a0b0c0d0e0f1011121314151617",
+        "dda97ca4864cdfe06eaf70a0ec0d7191",
+    );
+}
+
+#[test]
+fn test_fips_197_aes_256() {
+    check_fips_197_vector(
+        "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+        "8ea2b7ca516745bfeafc49904b496089",
+    );
+}
+
+#[test]
+fn test_fips_197_key_expansion() {
+    let schedule = KeySchedule::new(&hex_to_bytes("2b7e151628aed2a6abf7158809cf4f3c"));
+    let round_keys = schedule.round_keys();
+
+    assert_eq!(schedule.rounds(), 10);
+    assert_eq!(
+        bytes_to_hex(&round_keys[1]),
+        "a0fafe1788542cb123a339392a6c7605"
+    );
+    assert_eq!(
+        bytes_to_hex(&round_keys[10]),
+        "d014f9a8c9ee2589e13f0cc8b6630ca6"
+    );
+}
+
+#[test]
+fn test_reduced_rounds_roundtrip() {
+    let schedule = KeySchedule::with_rounds(b"YELLOW SUBMARINE", 4);
+    let mut block = *b"four round block";
+
+    schedule.encrypt_block(&mut block);
+    assert_ne!(&block, b"four round block");
+
+    schedule.decrypt_block(&mut block);
+    assert_eq!(&block, b"four round block");
+}
@@ -6,6 +6,8 @@ use rug::{
 };
 use std::convert::TryInto;
 
+use crate::error::{Error, Result};
+
 pub fn modexp(base: &Integer, exponent: &Integer, modulus: &Integer) -> Integer {
     if modulus == &1 {
         return Integer::from(0);
@@ -51,23 +53,41 @@ pub fn random_integer<R: Rng>(modulus: &Integer, rng: &mut R) -> Integer {
 }
 
 pub fn random_prime(bits: u32) -> Integer {
+    try_random_prime(bits).unwrap_or_else(|err| panic!("Can't generate prime: {}", err))
+}
+
+pub fn try_random_prime(bits: u32) -> Result<Integer> {
     use openssl::bn::BigNum;
 
-    let mut openssl_num = BigNum::new().unwrap();
-    openssl_num
-        .generate_prime(bits.try_into().expect("i32 overflow"), false, None, None)
-        .unwrap();
+    let bits: i32 = bits
+        .try_into()
+        .map_err(|_| Error::Backend(format!("Too many bits for a prime: {}", bits)))?;
+
+    let mut openssl_num = BigNum::new()?;
+    openssl_num.generate_prime(bits, false, None, None)?;
     let bytes = openssl_num.to_vec();
 
     let mut prime = Integer::with_capacity(bytes.len() * 8);
     prime.assign_digits(&bytes, Order::Msf);
-    prime
+    Ok(prime)
 }
 
 pub fn from_hex(hex: &str) -> Integer {
+    try_from_hex(hex).unwrap_or_else(|err| panic!("Invalid hex number: {}", err))
+}
+
+pub fn try_from_hex(hex: &str) -> Result<Integer> {
     let mut integer = Integer::new();
-    integer.assign(Integer::parse_radix(hex, 16).unwrap());
-    integer
+
+    let parsed = Integer::parse_radix(hex, 16).map_err(|_| Error::InvalidEncoding {
+        position: hex
+            .chars()
+            .position(|chr| !chr.is_ascii_hexdigit())
+            .unwrap_or(0),
+    })?;
+
+    integer.assign(parsed);
+    Ok(integer)
 }
 
 pub fn from_bytes(bytes: &[u8]) -> Integer {
@@ -81,9 +101,13 @@ pub fn to_bytes(integer: &Integer) -> Vec<u8> {
 }
 
 pub fn invmod(x: &Integer, m: &Integer) -> Integer {
+    try_invmod(x, m).unwrap_or_else(|_| panic!("Numbers aren't coprime"))
+}
+
+pub fn try_invmod(x: &Integer, m: &Integer) -> Result<Integer> {
     let result = egcd(x, m);
     if result.gcd != 1 {
-        panic!("Numbers aren't coprime");
+        return Err(Error::NotInvertible);
     }
 
     let mut result = result.s_coefficient;
@@ -92,7 +116,7 @@ pub fn invmod(x: &Integer, m: &Integer) -> Integer {
         result += m;
     }
 
-    result
+    Ok(result)
 }
 
 pub fn lcm(a: &Integer, b: &Integer) -> Integer {
@@ -17,6 +17,7 @@ mod secret {
     use rand::prelude::*;
 
     use cryptopals::aes::{aes_128_cbc_decrypt, aes_128_cbc_encrypt};
+    use cryptopals::Error;
 
     const BLOCK_SIZE: usize = 16;
 
@@ -49,7 +50,7 @@ mod secret {
             aes_128_cbc_encrypt(&complete_plaintext, &self.key, &IV)
         }
 
-        pub fn is_admin(&self, ciphertext: &[u8]) -> Result<bool, &'static str> {
+        pub fn is_admin(&self, ciphertext: &[u8]) -> Result<bool, Error> {
             let plaintext = aes_128_cbc_decrypt(ciphertext, &self.key, &IV)?;
             let plaintext_str = String::from_utf8_lossy(&plaintext);
 
@@ -19,7 +19,7 @@ fn main() {
         previous_block = ciphertext;
     }
 
-    let unpadded = pkcs7_unpad(&plaintext).unwrap();
+    let unpadded = pkcs7_unpad(&plaintext, BLOCK_SIZE).unwrap();
     println!("{}", String::from_utf8_lossy(&unpadded));
 }
 
@@ -81,7 +81,7 @@ fn discover_block(
 mod secret {
     use rand::prelude::*;
 
-    use cryptopals::{aes, encoding};
+    use cryptopals::{aes, encoding, Error};
 
     pub struct Oracle {
         key: [u8; 16],
@@ -138,7 +138,7 @@ mod secret {
                     // println!("{}", block_pretty_print(&p));
                     true
                 }
-                Err("Bad Padding") => false,
+                Err(Error::BadPadding) => false,
                 Err(e) => panic!("Unexpected error from decryption: {:?}", e),
             }
         }
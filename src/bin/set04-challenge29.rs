@@ -1,10 +1,4 @@
-use std::convert::TryInto;
-use std::iter::repeat;
-use std::mem;
-
-use cryptopals::{padding, prelude::*, sha1};
-
-const U32_SIZE: usize = mem::size_of::<u32>();
+use cryptopals::{length_extension, prelude::*, sha1};
 
 const SUFFIX: &[u8] = b";admin=true";
 
@@ -12,35 +6,16 @@ fn main() {
     let validator = secret::new_validator();
     let original_mac = validator.generate_cookie_mac();
 
-    let mut valid_admin_cookie = None;
-    let mut valid_admin_mac = None;
-
-    for secret_length in 1..50 {
-        let mut message = add_glue_padding(&secret::ORIGINAL_COOKIE, secret_length);
-
-        let mut initialized_sha1 = sha1::SHA1::with_registers(
-            to_u32(&original_mac, 0),
-            to_u32(&original_mac, U32_SIZE),
-            to_u32(&original_mac, U32_SIZE * 2),
-            to_u32(&original_mac, U32_SIZE * 3),
-            to_u32(&original_mac, U32_SIZE * 4),
-        );
-
-        let total_original_length = secret_length + message.len();
-        initialized_sha1.set_processed_bits((total_original_length * 8) as u64);
-        initialized_sha1.update(SUFFIX);
-        let new_mac = initialized_sha1.finalize();
-
-        message.extend_from_slice(SUFFIX);
-
-        if validator.validate(&message, &new_mac) {
-            valid_admin_cookie = Some(message);
-            valid_admin_mac = Some(new_mac);
-            break;
-        }
-    }
+    let (admin_cookie, admin_mac) = length_extension::find_forgery::<sha1::SHA1, _>(
+        &original_mac,
+        secret::ORIGINAL_COOKIE,
+        1..50,
+        SUFFIX,
+        |cookie, mac| validator.validate(cookie, mac),
+    )
+    .unwrap();
 
-    let new_cookie = String::from_utf8_lossy(valid_admin_cookie.as_ref().unwrap());
+    let new_cookie = String::from_utf8_lossy(&admin_cookie);
 
     println!(
         "{} New cookie contains \";admin=true\"",
@@ -48,25 +23,10 @@ fn main() {
     );
     println!(
         "{} New MAC is valid",
-        check_mark(validator.validate(&valid_admin_cookie.unwrap(), &valid_admin_mac.unwrap()))
+        check_mark(validator.validate(&admin_cookie, &admin_mac))
     );
 }
 
-fn add_glue_padding(message: &[u8], secret_length: usize) -> Vec<u8> {
-    let with_fake_secret: Vec<_> = repeat(b'A')
-        .take(secret_length)
-        .chain(message.iter().copied())
-        .collect();
-
-    let mut padded = padding::md_padding(&with_fake_secret, 0);
-    padded.split_off(secret_length)
-}
-
-fn to_u32(slice: &[u8], offset: usize) -> u32 {
-    let bytes = &slice[offset..offset + U32_SIZE];
-    u32::from_be_bytes(bytes.try_into().unwrap())
-}
-
 mod secret {
     use cryptopals::sha1;
     use rand::prelude::*;
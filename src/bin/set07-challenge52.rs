@@ -0,0 +1,40 @@
+use cryptopals::{
+    hasher::Hasher,
+    multicollision::{self, Multicollision},
+    prelude::*,
+    toy_hash::{ToyHash16, ToyHash32},
+};
+
+fn main() {
+    let multicollision = Multicollision::<ToyHash16>::new(8);
+    let digest = ToyHash16::digest(&multicollision.message(0));
+
+    println!(
+        "{} {} messages collide in the 16-bit hash after {} collision searches",
+        check_mark(
+            multicollision
+                .messages()
+                .all(|message| ToyHash16::digest(&message) == digest)
+        ),
+        multicollision.message_count(),
+        multicollision.collisions().len()
+    );
+
+    let (left, right, collision_count) =
+        multicollision::find_cascade_collision::<ToyHash16, ToyHash32>();
+
+    println!(
+        "Cascade collision after {} collision searches in the 16-bit hash:\n{}\n{}",
+        collision_count,
+        bytes_to_hex(&left),
+        bytes_to_hex(&right)
+    );
+    println!(
+        "{} Both hashes collide",
+        check_mark(
+            left != right
+                && ToyHash16::digest(&left) == ToyHash16::digest(&right)
+                && ToyHash32::digest(&left) == ToyHash32::digest(&right)
+        )
+    );
+}
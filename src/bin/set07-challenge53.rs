@@ -0,0 +1,26 @@
+use cryptopals::{hasher::Hasher, prelude::*, second_preimage, toy_hash::ToyHash24};
+use rand::prelude::*;
+
+// 2^16 blocks, so the bridge block only needs around 2^8 tries against a 24-bit hash
+const TARGET_BLOCKS: usize = 1 << 16;
+
+fn main() {
+    let mut target = vec![0; TARGET_BLOCKS * ToyHash24::BLOCK_SIZE];
+    thread_rng().fill(&mut target[..]);
+
+    let forged = second_preimage::find_second_preimage::<ToyHash24>(&target);
+
+    println!(
+        "{} Forged message is different from the target",
+        check_mark(forged != target)
+    );
+    println!(
+        "{} Forged message has the same length as the target",
+        check_mark(forged.len() == target.len())
+    );
+    println!(
+        "{} Forged message has the same hash as the target: {}",
+        check_mark(ToyHash24::digest(&forged) == ToyHash24::digest(&target)),
+        bytes_to_hex(&ToyHash24::digest(&forged))
+    );
+}
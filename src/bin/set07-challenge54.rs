@@ -0,0 +1,24 @@
+use cryptopals::{hasher::Hasher, herding::Prediction, prelude::*, toy_hash::ToyHash24};
+
+const SCORES: &[&str] = &[
+    "Final scores: Cubs 3, Mets 2; Yankees 7, Red Sox 4; Dodgers 1, Giants 0",
+    "Final scores: Cubs 0, Mets 11; Yankees 2, Red Sox 2; Dodgers 5, Giants 6",
+    "Final scores: Cubs 4, Mets 4; Yankees 1, Red Sox 9; Dodgers 3, Giants 2",
+];
+
+fn main() {
+    // Before the season: publish a hash, without knowing any of the results
+    let prediction = Prediction::<ToyHash24>::new(10, 80);
+    println!("Prediction: {}", bytes_to_hex(prediction.digest()));
+
+    // After the games: whichever way they went, there's a message that matches
+    for scores in SCORES {
+        let message = prediction.herd(scores.as_bytes());
+
+        println!(
+            "{} {}",
+            check_mark(ToyHash24::digest(&message) == prediction.digest()),
+            scores
+        );
+    }
+}
@@ -0,0 +1,15 @@
+use cryptopals::{md4::md4, md4_collision, prelude::*};
+
+fn main() {
+    let (block, other, attempts) = md4_collision::find_collision();
+
+    println!("Found a collision after {} candidates:", attempts);
+    println!("{}", bytes_to_hex(&block));
+    println!("{}", bytes_to_hex(&other));
+    println!("MD4: {}", bytes_to_hex(&md4(&block)));
+
+    println!(
+        "{} Messages are different and have the same MD4 hash",
+        check_mark(block != other && md4(&block) == md4(&other))
+    );
+}
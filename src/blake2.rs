@@ -0,0 +1,449 @@
+use std::convert::TryInto;
+use std::mem;
+
+use crate::bytes::constant_time_eq;
+use crate::error::{or_panic, Error, Result};
+use crate::hasher::Hasher;
+
+// Message word permutations, one per round. BLAKE2b has two more rounds than there are rows, and
+// wraps around
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+// BLAKE2 (RFC 7693) is keyed natively: the key is padded to a full block and hashed as the first
+// block, so unlike a secret-prefix MAC there's nothing to extend (the last block is marked as
+// such), and unlike HMAC it only takes one pass. Both variants share everything but the word
+// size and constants
+macro_rules! blake2_variant {
+    (
+        $name:ident,
+        $function:ident,
+        $mac_function:ident,
+        word: $word:ty,
+        rounds: $rounds:expr,
+        rotations: ($r1:expr, $r2:expr, $r3:expr, $r4:expr),
+        iv: $iv:expr,
+        salt_size: $salt_size:expr
+    ) => {
+        pub struct $name {
+            h: [$word; 8],
+            processed_bytes: u128,
+            incomplete_block: Vec<u8>,
+            digest_len: usize,
+        }
+
+        impl $name {
+            pub const BLOCK_SIZE: usize = 16 * mem::size_of::<$word>();
+            pub const MAX_DIGEST_SIZE: usize = 8 * mem::size_of::<$word>();
+            pub const MAX_KEY_SIZE: usize = Self::MAX_DIGEST_SIZE;
+            pub const SALT_SIZE: usize = $salt_size;
+            pub const PERSONAL_SIZE: usize = $salt_size;
+
+            pub fn new(digest_len: usize) -> Self {
+                Self::with_params(digest_len, &[], &[], &[])
+            }
+
+            pub fn with_key(digest_len: usize, key: &[u8]) -> Self {
+                Self::with_params(digest_len, key, &[], &[])
+            }
+
+            pub fn with_params(
+                digest_len: usize,
+                key: &[u8],
+                salt: &[u8],
+                personal: &[u8],
+            ) -> Self {
+                or_panic(Self::try_with_params(digest_len, key, salt, personal))
+            }
+
+            // Salt and personalization can be shorter than their full size, and get zero-padded
+            pub fn try_with_params(
+                digest_len: usize,
+                key: &[u8],
+                salt: &[u8],
+                personal: &[u8],
+            ) -> Result<Self> {
+                if digest_len == 0 || digest_len > Self::MAX_DIGEST_SIZE {
+                    return Err(Error::InvalidLength {
+                        expected: Self::MAX_DIGEST_SIZE,
+                        actual: digest_len,
+                    });
+                }
+
+                for &(param, max_len) in [
+                    (key, Self::MAX_KEY_SIZE),
+                    (salt, Self::SALT_SIZE),
+                    (personal, Self::PERSONAL_SIZE),
+                ]
+                .iter()
+                {
+                    if param.len() > max_len {
+                        return Err(Error::InvalidLength {
+                            expected: max_len,
+                            actual: param.len(),
+                        });
+                    }
+                }
+
+                // The parameter block, of which only the first word and the salt and
+                // personalization differ from zero
+                let mut h = $iv;
+                h[0] ^= 0x0101_0000 ^ ((key.len() as $word) << 8) ^ digest_len as $word;
+
+                let words = Self::words(&padded(salt, Self::SALT_SIZE));
+                h[4] ^= words[0];
+                h[5] ^= words[1];
+
+                let words = Self::words(&padded(personal, Self::PERSONAL_SIZE));
+                h[6] ^= words[0];
+                h[7] ^= words[1];
+
+                let mut hasher = Self {
+                    h,
+                    processed_bytes: 0,
+                    incomplete_block: Vec::with_capacity(Self::BLOCK_SIZE),
+                    digest_len,
+                };
+
+                if !key.is_empty() {
+                    hasher.update(&padded(key, Self::BLOCK_SIZE));
+                }
+
+                Ok(hasher)
+            }
+
+            // The last block has to be compressed differently, so a full block is only
+            // processed once there's more data after it
+            pub fn update(&mut self, mut message: &[u8]) {
+                if self.incomplete_block.len() + message.len() <= Self::BLOCK_SIZE {
+                    self.incomplete_block.extend_from_slice(message);
+                    return;
+                }
+
+                if !self.incomplete_block.is_empty() {
+                    let (left, right) =
+                        message.split_at(Self::BLOCK_SIZE - self.incomplete_block.len());
+                    let mut block = mem::take(&mut self.incomplete_block);
+                    block.extend_from_slice(left);
+
+                    self.process_block(&block, false);
+                    message = right;
+                }
+
+                while message.len() > Self::BLOCK_SIZE {
+                    let (block, rest) = message.split_at(Self::BLOCK_SIZE);
+                    self.process_block(block, false);
+                    message = rest;
+                }
+
+                self.incomplete_block.extend_from_slice(message);
+            }
+
+            pub fn finalize(mut self) -> Vec<u8> {
+                let last_block = mem::take(&mut self.incomplete_block);
+                self.process_block(&last_block, true);
+
+                let mut digest: Vec<u8> = self.h.iter().flat_map(|h| h.to_le_bytes()).collect();
+                digest.truncate(self.digest_len);
+                digest
+            }
+
+            pub fn verify(self, tag: &[u8]) -> bool {
+                constant_time_eq(&self.finalize(), tag)
+            }
+
+            fn process_block(&mut self, block: &[u8], is_last: bool) {
+                self.processed_bytes += block.len() as u128;
+
+                let m = Self::words(&padded(block, Self::BLOCK_SIZE));
+
+                let mut v = [0; 16];
+                v[..8].copy_from_slice(&self.h);
+                v[8..].copy_from_slice(&$iv);
+
+                v[12] ^= self.processed_bytes as $word;
+                v[13] ^= (self.processed_bytes >> <$word>::BITS) as $word;
+                if is_last {
+                    v[14] = !v[14];
+                }
+
+                for round in 0..$rounds {
+                    let s = &SIGMA[round % 10];
+
+                    Self::mix(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+                    Self::mix(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+                    Self::mix(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+                    Self::mix(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+
+                    Self::mix(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+                    Self::mix(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+                    Self::mix(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+                    Self::mix(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+                }
+
+                for (index, h) in self.h.iter_mut().enumerate() {
+                    *h ^= v[index] ^ v[index + 8];
+                }
+            }
+
+            // The G function
+            fn mix(
+                v: &mut [$word; 16],
+                a: usize,
+                b: usize,
+                c: usize,
+                d: usize,
+                x: $word,
+                y: $word,
+            ) {
+                v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+                v[d] = (v[d] ^ v[a]).rotate_right($r1);
+                v[c] = v[c].wrapping_add(v[d]);
+                v[b] = (v[b] ^ v[c]).rotate_right($r2);
+                v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+                v[d] = (v[d] ^ v[a]).rotate_right($r3);
+                v[c] = v[c].wrapping_add(v[d]);
+                v[b] = (v[b] ^ v[c]).rotate_right($r4);
+            }
+
+            fn words(bytes: &[u8]) -> Vec<$word> {
+                bytes
+                    .chunks(mem::size_of::<$word>())
+                    .map(|word| <$word>::from_le_bytes(word.try_into().unwrap()))
+                    .collect()
+            }
+        }
+
+        pub fn $function(message: &[u8], digest_len: usize) -> Vec<u8> {
+            let mut hasher = $name::new(digest_len);
+            hasher.update(message);
+            hasher.finalize()
+        }
+
+        // Keyed mode, as a MAC
+        pub fn $mac_function(key: &[u8], message: &[u8], digest_len: usize) -> Vec<u8> {
+            let mut hasher = $name::with_key(digest_len, key);
+            hasher.update(message);
+            hasher.finalize()
+        }
+    };
+}
+
+blake2_variant!(
+    Blake2b,
+    blake2b,
+    blake2b_mac,
+    word: u64,
+    rounds: 12,
+    rotations: (32, 24, 16, 63),
+    iv: [
+        0x6a09e667f3bcc908,
+        0xbb67ae8584caa73b,
+        0x3c6ef372fe94f82b,
+        0xa54ff53a5f1d36f1,
+        0x510e527fade682d1,
+        0x9b05688c2b3e6c1f,
+        0x1f83d9abfb41bd6b,
+        0x5be0cd19137e2179,
+    ],
+    salt_size: 16
+);
+
+blake2_variant!(
+    Blake2s,
+    blake2s,
+    blake2s_mac,
+    word: u32,
+    rounds: 10,
+    rotations: (16, 12, 8, 7),
+    iv: [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ],
+    salt_size: 8
+);
+
+// Unkeyed BLAKE2 at its full digest size, so it fits anywhere a `Hasher` does: HMAC, HKDF, the
+// collision searches. It isn't `MerkleDamgard`, since the last block is flagged rather than padded,
+// and running the compression function over the message on its own doesn't give the digest
+macro_rules! blake2_hasher {
+    ($name:ident, $inner:ident, $digest_len:expr) => {
+        pub struct $name($inner);
+
+        impl Hasher for $name {
+            const BLOCK_SIZE: usize = $inner::BLOCK_SIZE;
+            const OUTPUT_SIZE: usize = $digest_len;
+
+            fn new() -> Self {
+                Self($inner::new($digest_len))
+            }
+
+            fn update(&mut self, message: &[u8]) {
+                self.0.update(message)
+            }
+
+            fn finalize(self) -> Vec<u8> {
+                self.0.finalize()
+            }
+        }
+    };
+}
+
+blake2_hasher!(Blake2b512, Blake2b, 64);
+blake2_hasher!(Blake2s256, Blake2s, 32);
+
+fn padded(bytes: &[u8], len: usize) -> Vec<u8> {
+    let mut result = bytes.to_vec();
+    result.resize(len, 0);
+    result
+}
+
+#[cfg(test)]
+use crate::{
+    encoding::{bytes_to_hex, hex_to_bytes},
+    hmac::{self, Hmac},
+    kdf,
+};
+
+#[test]
+fn test_blake2_rfc7693_vectors() {
+    // Appendices A and B
+    assert_eq!(
+        bytes_to_hex(&blake2b(b"abc", 64)),
+        "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+         7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+    );
+    assert_eq!(
+        bytes_to_hex(&blake2s(b"abc", 32)),
+        "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982"
+    );
+
+    assert_eq!(
+        bytes_to_hex(&blake2b(b"", 64)),
+        "786a02f742015903c6c6fd852552d272912f4740e15847618a86e217f71f5419\
+         d25e1031afee585313896444934eb04b903a685b1448b755d56f701afe9be2ce"
+    );
+}
+
+#[test]
+fn test_blake2_block_boundaries() {
+    let message: Vec<u8> = (0..=255).collect();
+
+    // Exactly one block, which must be compressed as the last one
+    assert_eq!(
+        bytes_to_hex(&blake2b(&message[..128], 64)),
+        "2319e3789c47e2daa5fe807f61bec2a1a6537fa03f19ff32e87eecbfd64b7e0e\
+         8ccff439ac333b040f19b0c4ddd11a61e24ac1fe0f10a039806c5dcc0da3d115"
+    );
+    assert_eq!(
+        bytes_to_hex(&blake2s(&message[..64], 32)),
+        "56f34e8b96557e90c1f24b52d0c89d51086acf1b00f634cf1dde9233b8eaaa3e"
+    );
+
+    let mut blake2s = Blake2s::with_key(32, &message[..32]);
+    for chunk in message.chunks(64) {
+        blake2s.update(chunk);
+    }
+    assert_eq!(
+        bytes_to_hex(&blake2s.finalize()),
+        "5211d1aefc0025be7f85c06b3e14e0fc645ae12bd41746485ea6d8a364a2eaee"
+    );
+}
+
+#[test]
+fn test_blake2_keyed_with_parameters() {
+    let message = b"The quick brown fox jumps over the lazy dog";
+
+    assert_eq!(
+        bytes_to_hex(&blake2b_mac(
+            &(0..64).collect::<Vec<u8>>(),
+            &(0..=255).collect::<Vec<u8>>(),
+            64
+        )),
+        "b72071e096277edebb8ee5134dd3714996307ba3a55aa4733d412abbe28e909e\
+         10e57e6fbfb4ef53b3b960518294ff889a90829254412e2a60b85add07a3674f"
+    );
+
+    let mut blake2b = Blake2b::with_params(32, b"YELLOW SUBMARINE", b"saltsalt", b"cryptopals");
+    blake2b.update(message);
+    assert_eq!(
+        bytes_to_hex(&blake2b.finalize()),
+        "f252fa6025c0a851d94f13f15368428af20667784b12b19ba48497f8914f1973"
+    );
+
+    let mut blake2s = Blake2s::with_params(16, b"YELLOW SUBMARINE", b"salt", b"pals");
+    blake2s.update(message);
+    assert_eq!(
+        bytes_to_hex(&blake2s.finalize()),
+        "d4c6adcba0dec9bcd249c9644830265b"
+    );
+
+    let tag = blake2s_mac(b"YELLOW SUBMARINE", message, 32);
+    let mut blake2s = Blake2s::with_key(32, b"YELLOW SUBMARINE");
+    blake2s.update(message);
+    assert!(blake2s.verify(&tag));
+
+    assert_eq!(
+        Blake2s::try_with_params(32, &[0; 33], &[], &[]).err(),
+        Some(Error::InvalidLength {
+            expected: 32,
+            actual: 33
+        })
+    );
+    assert_eq!(
+        Blake2b::try_with_params(65, &[], &[], &[]).err(),
+        Some(Error::InvalidLength {
+            expected: 64,
+            actual: 65
+        })
+    );
+}
+
+#[test]
+fn test_blake2_as_a_drop_in_hasher() {
+    let message: Vec<u8> = (0..=255).collect();
+    assert_eq!(Blake2b512::digest(&message), blake2b(&message, 64));
+    assert_eq!(Blake2s256::digest(&message), blake2s(&message, 32));
+    assert_eq!(
+        bytes_to_hex(&Blake2b512::digest(&message)),
+        "1ecc896f34d3f9cac484c73f75f6a5fb58ee6784be41b35f46067b9c65c63a67\
+         94d3d744112c653f73dd7deb6666204c5a9bfa5b46081fc10fdbe7884fa5cbf8"
+    );
+
+    // Wherever HMAC-SHA256 goes, HMAC-BLAKE2s fits too
+    let message = b"The quick brown fox jumps over the lazy dog";
+    let mut hmac = Hmac::<Blake2s256>::new(b"key");
+    for chunk in message.chunks(10) {
+        hmac.update(chunk);
+    }
+    let tag = hmac.finalize();
+    assert_eq!(
+        bytes_to_hex(&tag),
+        "f93215bb90d4af4c3061cd932fb169fb8bb8a91d0b4022baea1271e1323cd9a0"
+    );
+    assert_eq!(hmac::hmac::<Blake2s256>(b"key", message), tag);
+
+    let mut hmac = Hmac::<Blake2s256>::new(b"key");
+    hmac.update(message);
+    assert!(hmac.verify(&tag));
+
+    // RFC 5869's A.1 inputs, with BLAKE2s in place of SHA-256
+    let ikm = [0x0b; 22];
+    let salt = hex_to_bytes("000102030405060708090a0b0c");
+    let info = hex_to_bytes("f0f1f2f3f4f5f6f7f8f9");
+    assert_eq!(
+        bytes_to_hex(&kdf::hkdf::<Blake2s256>(&salt, &ikm, &info, 42)),
+        "1472c31f2ff768c71b19f8803683ee3b13c1a5fb3ea59c0c3bf0d44a4a40dcd4\
+         329d9cd85bbe35a1b3e7"
+    );
+}
@@ -0,0 +1,18 @@
+pub trait BlockCipher {
+    const BLOCK_SIZE: usize;
+
+    fn encrypt_block(&self, block: &mut [u8]);
+    fn decrypt_block(&self, block: &mut [u8]);
+
+    fn encrypt_blocks(&self, blocks: &mut [u8]) {
+        for block in blocks.chunks_mut(Self::BLOCK_SIZE) {
+            self.encrypt_block(block);
+        }
+    }
+
+    fn decrypt_blocks(&self, blocks: &mut [u8]) {
+        for block in blocks.chunks_mut(Self::BLOCK_SIZE) {
+            self.decrypt_block(block);
+        }
+    }
+}
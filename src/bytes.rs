@@ -1,15 +1,27 @@
+use crate::error::{or_panic, Error, Result};
+
 pub fn every_nth_byte(bytes: &[u8], n: usize) -> Vec<u8> {
+    or_panic(try_every_nth_byte(bytes, n))
+}
+
+pub fn try_every_nth_byte(bytes: &[u8], n: usize) -> Result<Vec<u8>> {
+    check_nonzero(n)?;
+
     let mut result = Vec::with_capacity(bytes.len() / n);
 
     for index in (0..bytes.len()).step_by(n) {
         result.push(bytes[index]);
     }
 
-    result
+    Ok(result)
 }
 
 pub fn any_repeated_block(bytes: &[u8], block_size: usize) -> bool {
-    let blocks = bytes.len() / block_size;
+    or_panic(try_any_repeated_block(bytes, block_size))
+}
+
+pub fn try_any_repeated_block(bytes: &[u8], block_size: usize) -> Result<bool> {
+    let blocks = check_block_count(bytes, block_size)?;
 
     for block1_number in 0..(blocks - 1) {
         let block1 = &bytes[(block1_number * block_size)..((block1_number + 1) * block_size)];
@@ -18,10 +30,80 @@ pub fn any_repeated_block(bytes: &[u8], block_size: usize) -> bool {
             let block2 = &bytes[(block2_number * block_size)..((block2_number + 1) * block_size)];
 
             if block1 == block2 {
-                return true;
+                return Ok(true);
             }
         }
     }
 
-    return false;
+    Ok(false)
+}
+
+fn check_nonzero(size: usize) -> Result<()> {
+    if size == 0 {
+        return Err(Error::InvalidLength {
+            expected: 1,
+            actual: 0,
+        });
+    }
+
+    Ok(())
+}
+
+// Comparing blocks takes at least two of them. Returns how many whole blocks there are
+pub(crate) fn check_block_count(bytes: &[u8], block_size: usize) -> Result<usize> {
+    check_nonzero(block_size)?;
+
+    let blocks = bytes.len() / block_size;
+    if blocks < 2 {
+        return Err(Error::InvalidLength {
+            expected: 2 * block_size,
+            actual: bytes.len(),
+        });
+    }
+
+    Ok(blocks)
+}
+
+// Compares without short-circuiting on the first difference, so the time taken doesn't reveal
+// how long the matching prefix is. Only the lengths themselves leak
+pub fn constant_time_eq(bytes1: &[u8], bytes2: &[u8]) -> bool {
+    if bytes1.len() != bytes2.len() {
+        return false;
+    }
+
+    let difference = bytes1
+        .iter()
+        .zip(bytes2)
+        .fold(0, |acc, (a, b)| acc | (a ^ b));
+
+    difference == 0
+}
+
+#[test]
+fn test_block_helpers_reject_bad_sizes() {
+    assert_eq!(every_nth_byte(b"abcdefg", 3), b"adg");
+    assert_eq!(
+        try_every_nth_byte(b"abc", 0),
+        Err(Error::InvalidLength {
+            expected: 1,
+            actual: 0
+        })
+    );
+
+    assert!(any_repeated_block(b"abcdXXXXabcd", 4));
+    assert!(!any_repeated_block(b"abcdXXXXabce", 4));
+    assert_eq!(
+        try_any_repeated_block(b"abcdab", 4),
+        Err(Error::InvalidLength {
+            expected: 8,
+            actual: 6
+        })
+    );
+    assert_eq!(
+        try_any_repeated_block(b"abcdabcd", 0),
+        Err(Error::InvalidLength {
+            expected: 1,
+            actual: 0
+        })
+    );
 }
@@ -0,0 +1,114 @@
+use crate::block_cipher::BlockCipher;
+use crate::bytes::constant_time_eq;
+use crate::xor::xor_in_place;
+
+// CMAC (SP 800-38B, RFC 4493): CBC-MAC with a zero IV, where the last block is masked with one of
+// two subkeys depending on whether it had to be padded. That's what makes it safe for messages
+// of different lengths, unlike plain CBC-MAC
+pub struct Cmac<C> {
+    cipher: C,
+    k1: Vec<u8>,
+    k2: Vec<u8>,
+}
+
+impl<C: BlockCipher> Cmac<C> {
+    pub fn new(cipher: C) -> Self {
+        let mut k1 = vec![0; C::BLOCK_SIZE];
+        cipher.encrypt_block(&mut k1);
+        dbl(&mut k1);
+
+        let mut k2 = k1.clone();
+        dbl(&mut k2);
+
+        Self { cipher, k1, k2 }
+    }
+
+    pub fn cipher(&self) -> &C {
+        &self.cipher
+    }
+
+    pub fn compute(&self, message: &[u8]) -> Vec<u8> {
+        let block_size = C::BLOCK_SIZE;
+
+        // The last block is the only one that gets special treatment, and an empty message still
+        // has one (all padding)
+        let last_start = if message.is_empty() {
+            0
+        } else {
+            (message.len() - 1) / block_size * block_size
+        };
+        let (head, last) = message.split_at(last_start);
+
+        let mut state = vec![0; block_size];
+        for block in head.chunks(block_size) {
+            xor_in_place(&mut state, block);
+            self.cipher.encrypt_block(&mut state);
+        }
+
+        let mut last_block = last.to_vec();
+        if last_block.len() == block_size {
+            xor_in_place(&mut last_block, &self.k1);
+        } else {
+            last_block.push(0x80);
+            last_block.resize(block_size, 0);
+            xor_in_place(&mut last_block, &self.k2);
+        }
+
+        xor_in_place(&mut state, &last_block);
+        self.cipher.encrypt_block(&mut state);
+        state
+    }
+
+    pub fn verify(&self, message: &[u8], tag: &[u8]) -> bool {
+        constant_time_eq(&self.compute(message), tag)
+    }
+}
+
+// Multiplication by x in GF(2^n), with the block read as a big-endian number. Also used by S2V
+pub(crate) fn dbl(block: &mut [u8]) {
+    let reduction = match block.len() {
+        8 => 0x1b,
+        16 => 0x87,
+        len => panic!("No CMAC subkey polynomial for {}-byte blocks", len),
+    };
+
+    let carry = block[0] >> 7;
+
+    for index in 0..block.len() - 1 {
+        block[index] = (block[index] << 1) | (block[index + 1] >> 7);
+    }
+
+    let last = block.len() - 1;
+    block[last] = (block[last] << 1) ^ (carry * reduction);
+}
+
+#[test]
+fn test_cmac_rfc4493_vectors() {
+    use crate::aes::Aes128;
+    use crate::encoding::{bytes_to_hex, hex_to_bytes};
+
+    let cmac = Cmac::new(Aes128::new(&hex_to_bytes(
+        "2b7e151628aed2a6abf7158809cf4f3c",
+    )));
+    let message = hex_to_bytes(
+        "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+         30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+    );
+
+    assert_eq!(bytes_to_hex(&cmac.k1), "fbeed618357133667c85e08f7236a8de");
+    assert_eq!(bytes_to_hex(&cmac.k2), "f7ddac306ae266ccf90bc11ee46d513b");
+
+    let vectors = [
+        (0, "bb1d6929e95937287fa37d129b756746"),
+        (16, "070a16b46b4d4144f79bdd9dd04a287c"),
+        (40, "dfa66747de9ae63030ca32611497c827"),
+        (64, "51f0bebf7e3b9d92fc49741779363cfe"),
+    ];
+
+    for &(len, expected) in vectors.iter() {
+        assert_eq!(bytes_to_hex(&cmac.compute(&message[..len])), expected);
+        assert!(cmac.verify(&message[..len], &hex_to_bytes(expected)));
+    }
+
+    assert!(!cmac.verify(&message[..16], &hex_to_bytes(vectors[0].1)));
+}
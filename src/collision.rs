@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use rand::{thread_rng, Rng};
+use rayon::prelude::*;
+
+use crate::hmac::HashFunction;
+
+// Generic collision searches on a hash truncated to its first `bits` bits. None of them know
+// anything about the hash, so they all take on the order of 2^(bits / 2) evaluations: the
+// birthday bound. They differ in how much memory they need to get there
+#[derive(Clone, Debug)]
+pub struct Collision {
+    pub messages: [Vec<u8>; 2],
+    pub digest: Vec<u8>,
+    pub bits: usize,
+    // How many times the search called the hash function
+    pub evaluations: u64,
+}
+
+impl Collision {
+    // The expected number of evaluations for the hash table search, sqrt(pi / 2 * 2^bits). The
+    // cycle-finding searches spend a small constant factor more, in exchange for their memory
+    pub fn expected_evaluations(&self) -> f64 {
+        (PI / 2.0 * 2_f64.powi(self.bits as i32)).sqrt()
+    }
+}
+
+// The first `bits` bits of the digest, with whatever's left of the last byte zeroed
+pub fn truncated_digest<H: HashFunction>(message: &[u8], bits: usize) -> Vec<u8> {
+    assert!(
+        bits > 0 && bits <= H::OUTPUT_SIZE * 8,
+        "Can't truncate a {}-bit digest to {} bits",
+        H::OUTPUT_SIZE * 8,
+        bits
+    );
+
+    let mut digest = H::compute(message);
+    truncate(&mut digest, bits);
+    digest
+}
+
+fn truncate(bytes: &mut Vec<u8>, bits: usize) {
+    bytes.truncate(bits.div_ceil(8));
+
+    if !bits.is_multiple_of(8) {
+        let last = bytes.len() - 1;
+        bytes[last] &= 0xff << (8 - bits % 8);
+    }
+}
+
+fn random_point<R: Rng>(bits: usize, rng: &mut R) -> Vec<u8> {
+    let mut point = vec![0; bits.div_ceil(8)];
+    rng.fill(&mut point[..]);
+    truncate(&mut point, bits);
+    point
+}
+
+// Keeps every digest seen so far, and stops at the first repeat. As fast as it gets, but it
+// needs memory for about 2^(bits / 2) messages. Counting up from a random point gives distinct
+// messages every time
+pub fn birthday<H: HashFunction>(bits: usize) -> Collision {
+    let mut seen = HashMap::new();
+    let offset: u64 = thread_rng().gen();
+
+    for counter in 0_u64.. {
+        let message = offset.wrapping_add(counter).to_be_bytes().to_vec();
+        let digest = truncated_digest::<H>(&message, bits);
+
+        if let Some(other) = seen.insert(digest.clone(), message.clone()) {
+            return Collision {
+                messages: [other, message],
+                digest,
+                bits,
+                evaluations: counter + 1,
+            };
+        }
+    }
+
+    unreachable!()
+}
+
+// Iterating the truncated hash on its own output has to enter a cycle eventually, and the point
+// where the walk joins the cycle is reached from two different values: one on the tail, one at
+// the end of the cycle. Finding the cycle only needs a couple of points in memory.
+//
+// Given the start of a walk and a point some multiple of the cycle length ahead of it, walks both
+// forward in lockstep until the next step would land them on the same value
+fn locate_cycle_entry<H: HashFunction>(
+    start: Vec<u8>,
+    hare: Vec<u8>,
+    bits: usize,
+    evaluations: &mut u64,
+) -> Option<(Vec<u8>, Vec<u8>, Vec<u8>)> {
+    // The start was already on the cycle, so nothing leads into it from outside
+    if start == hare {
+        return None;
+    }
+
+    let (mut tortoise, mut hare) = (start, hare);
+    loop {
+        let next_tortoise = truncated_digest::<H>(&tortoise, bits);
+        let next_hare = truncated_digest::<H>(&hare, bits);
+        *evaluations += 2;
+
+        if next_tortoise == next_hare {
+            return Some((tortoise, hare, next_tortoise));
+        }
+
+        tortoise = next_tortoise;
+        hare = next_hare;
+    }
+}
+
+// Floyd's tortoise and hare: the hare moves two steps for each of the tortoise's, so they meet
+// once both are on the cycle, at a multiple of its length from the start
+pub fn floyd<H: HashFunction>(bits: usize) -> Collision {
+    let mut rng = thread_rng();
+    let mut evaluations = 0;
+
+    loop {
+        let start = random_point(bits, &mut rng);
+
+        let mut tortoise = truncated_digest::<H>(&start, bits);
+        let mut hare = truncated_digest::<H>(&tortoise, bits);
+        evaluations += 2;
+
+        while tortoise != hare {
+            tortoise = truncated_digest::<H>(&tortoise, bits);
+            hare = truncated_digest::<H>(&truncated_digest::<H>(&hare, bits), bits);
+            evaluations += 3;
+        }
+
+        if let Some((left, right, digest)) =
+            locate_cycle_entry::<H>(start, hare, bits, &mut evaluations)
+        {
+            return Collision {
+                messages: [left, right],
+                digest,
+                bits,
+                evaluations,
+            };
+        }
+    }
+}
+
+// Brent's variant: the tortoise teleports to the hare at every power of two, which gives the
+// exact cycle length with fewer evaluations than Floyd's
+pub fn brent<H: HashFunction>(bits: usize) -> Collision {
+    let mut rng = thread_rng();
+    let mut evaluations = 0;
+
+    loop {
+        let start = random_point(bits, &mut rng);
+
+        let mut tortoise = start.clone();
+        let mut hare = truncated_digest::<H>(&start, bits);
+        evaluations += 1;
+
+        let (mut power, mut cycle_len) = (1_u64, 1_u64);
+        while tortoise != hare {
+            if power == cycle_len {
+                tortoise = hare.clone();
+                power *= 2;
+                cycle_len = 0;
+            }
+
+            hare = truncated_digest::<H>(&hare, bits);
+            evaluations += 1;
+            cycle_len += 1;
+        }
+
+        let mut hare = start.clone();
+        for _ in 0..cycle_len {
+            hare = truncated_digest::<H>(&hare, bits);
+        }
+        evaluations += cycle_len;
+
+        if let Some((left, right, digest)) =
+            locate_cycle_entry::<H>(start, hare, bits, &mut evaluations)
+        {
+            return Collision {
+                messages: [left, right],
+                digest,
+                bits,
+                evaluations,
+            };
+        }
+    }
+}
+
+fn is_distinguished(point: &[u8], distinguished_bits: usize) -> bool {
+    let zeros: usize = point
+        .iter()
+        .position(|&byte| byte != 0)
+        .map_or(point.len() * 8, |index| {
+            index * 8 + point[index].leading_zeros() as usize
+        });
+
+    zeros >= distinguished_bits
+}
+
+// Van Oorschot and Wiener's parallel search: every thread walks from random starting points until
+// it reaches a distinguished point (one whose first `distinguished_bits` bits are zero), and only
+// those get stored, along with where the walk started. Two walks ending on the same distinguished
+// point merged somewhere along the way, and replaying them from their starts finds where. Memory
+// goes down by a factor of 2^distinguished_bits, at the cost of about that many evaluations per
+// walk on top of the birthday bound
+pub fn distinguished_points<H: HashFunction>(bits: usize, distinguished_bits: usize) -> Collision {
+    assert!(distinguished_bits < bits);
+
+    // Walks can also fall into a cycle with no distinguished points on it, so give up on the
+    // ones that get much longer than expected
+    let max_len = 20_u64 << distinguished_bits;
+
+    let evaluations = AtomicU64::new(0);
+    let points = Mutex::new(HashMap::new());
+
+    let walk = |start: &[u8]| -> Option<(Vec<u8>, u64)> {
+        let mut point = start.to_vec();
+        for len in 1..=max_len {
+            point = truncated_digest::<H>(&point, bits);
+            evaluations.fetch_add(1, Ordering::Relaxed);
+
+            if is_distinguished(&point, distinguished_bits) {
+                return Some((point, len));
+            }
+        }
+
+        None
+    };
+
+    let (messages, digest) = rayon::iter::repeat(())
+        .map_init(thread_rng, |rng, _| random_point(bits, rng))
+        .find_map_any(|start| {
+            let (end, len) = walk(&start)?;
+
+            let previous = points.lock().unwrap().insert(end, (start.clone(), len));
+            let (other_start, other_len) = previous?;
+
+            let mut local_evaluations = 0;
+            let collision = merge_walks::<H>(
+                (start, len),
+                (other_start, other_len),
+                bits,
+                &mut local_evaluations,
+            );
+            evaluations.fetch_add(local_evaluations, Ordering::Relaxed);
+
+            collision
+        })
+        .unwrap();
+
+    Collision {
+        messages,
+        digest,
+        bits,
+        evaluations: evaluations.into_inner(),
+    }
+}
+
+// Replays two walks that end on the same point from the same distance to it, until they merge
+fn merge_walks<H: HashFunction>(
+    (mut left, left_len): (Vec<u8>, u64),
+    (mut right, right_len): (Vec<u8>, u64),
+    bits: usize,
+    evaluations: &mut u64,
+) -> Option<([Vec<u8>; 2], Vec<u8>)> {
+    if left_len < right_len {
+        return merge_walks::<H>((right, right_len), (left, left_len), bits, evaluations);
+    }
+
+    for _ in 0..left_len - right_len {
+        left = truncated_digest::<H>(&left, bits);
+    }
+    *evaluations += left_len - right_len;
+
+    // One walk started on the other's path, so they never actually merged
+    if left == right {
+        return None;
+    }
+
+    loop {
+        let next_left = truncated_digest::<H>(&left, bits);
+        let next_right = truncated_digest::<H>(&right, bits);
+        *evaluations += 2;
+
+        if next_left == next_right {
+            return Some(([left, right], next_left));
+        }
+
+        left = next_left;
+        right = next_right;
+    }
+}
+
+#[cfg(test)]
+use crate::{md5::MD5, sha1::SHA1, sha256::SHA256};
+
+#[cfg(test)]
+fn check_collision<H: HashFunction>(collision: &Collision, bits: usize) {
+    let [left, right] = &collision.messages;
+
+    assert_ne!(left, right);
+    assert_eq!(collision.bits, bits);
+    assert_eq!(truncated_digest::<H>(left, bits), collision.digest);
+    assert_eq!(truncated_digest::<H>(right, bits), collision.digest);
+    assert!(collision.evaluations > 0);
+}
+
+#[test]
+fn test_truncated_digest() {
+    let digest = truncated_digest::<SHA256>(b"abc", 12);
+    assert_eq!(digest, [0xba, 0x70]);
+    assert_eq!(truncated_digest::<SHA256>(b"abc", 16), [0xba, 0x78]);
+
+    assert!(is_distinguished(&[0x00, 0x0f], 12));
+    assert!(!is_distinguished(&[0x00, 0x1f], 12));
+}
+
+#[test]
+fn test_collision_searches() {
+    check_collision::<SHA256>(&birthday::<SHA256>(24), 24);
+    check_collision::<SHA1>(&floyd::<SHA1>(20), 20);
+    check_collision::<MD5>(&brent::<MD5>(20), 20);
+    check_collision::<SHA256>(&distinguished_points::<SHA256>(20, 4), 20);
+
+    let collision = birthday::<SHA256>(16);
+    assert!((collision.expected_evaluations() - 320.8).abs() < 0.1);
+}
@@ -2,7 +2,7 @@ use rand::prelude::*;
 use rug::integer::Order;
 use rug::Integer;
 
-use crate::{bignum, sha1};
+use crate::{bignum, kdf, sha1, sha256::SHA256};
 
 #[derive(Debug, Clone)]
 pub struct PublicKey(pub Integer);
@@ -66,6 +66,20 @@ impl SessionKey {
         hash
     }
 
+    // Independent keys for each purpose, through HKDF-SHA256 with the label as its info string,
+    // so the encryption key says nothing about the MAC key and vice versa
+    pub fn derive_key(&self, label: &[u8], len: usize) -> Vec<u8> {
+        kdf::hkdf::<SHA256>(&[], &self.to_bytes(), label, len)
+    }
+
+    pub fn encryption_key(&self) -> Vec<u8> {
+        self.derive_key(b"encryption", 16)
+    }
+
+    pub fn mac_key(&self) -> Vec<u8> {
+        self.derive_key(b"mac", 32)
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         self.0.to_digits(Order::LsfLe)
     }
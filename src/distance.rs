@@ -1,6 +1,17 @@
+use crate::bytes::check_block_count;
+use crate::error::{or_panic, Error, Result};
+
 pub fn hamming_distance(bytes1: &[u8], bytes2: &[u8]) -> u32 {
+    try_hamming_distance(bytes1, bytes2)
+        .unwrap_or_else(|err| panic!("Can't compute hamming distance: {}", err))
+}
+
+pub fn try_hamming_distance(bytes1: &[u8], bytes2: &[u8]) -> Result<u32> {
     if bytes1.len() != bytes2.len() {
-        panic!("Can't compute hamming distance between strings with different lengths");
+        return Err(Error::LengthMismatch {
+            left: bytes1.len(),
+            right: bytes2.len(),
+        });
     }
 
     let mut result = 0;
@@ -17,25 +28,29 @@ pub fn hamming_distance(bytes1: &[u8], bytes2: &[u8]) -> u32 {
         }
     }
 
-    result
+    Ok(result)
 }
 
 pub fn average_hamming_distance(bytes: &[u8], block_size: usize) -> f64 {
+    or_panic(try_average_hamming_distance(bytes, block_size))
+}
+
+pub fn try_average_hamming_distance(bytes: &[u8], block_size: usize) -> Result<f64> {
+    let blocks = check_block_count(bytes, block_size)?;
     let mut total_distance = 0f64;
     let mut distances_computed = 0;
-    let blocks = bytes.len() / block_size;
 
     for block1_number in 0..(blocks - 1) {
         let block1 = &bytes[(block1_number * block_size)..((block1_number + 1) * block_size)];
 
         for block2_number in (block1_number + 1)..blocks {
             let block2 = &bytes[(block2_number * block_size)..((block2_number + 1) * block_size)];
-            total_distance += hamming_distance(block1, block2) as f64;
+            total_distance += try_hamming_distance(block1, block2)? as f64;
             distances_computed += 1;
         }
     }
 
-    total_distance / (distances_computed as f64)
+    Ok(total_distance / (distances_computed as f64))
 }
 
 #[test]
@@ -46,3 +61,30 @@ fn test_hamming_distance() {
     let result = hamming_distance(str1, str2);
     assert_eq!(result, 37);
 }
+
+#[test]
+fn test_hamming_distance_length_mismatch() {
+    assert_eq!(
+        try_hamming_distance(b"abc", b"ab"),
+        Err(Error::LengthMismatch { left: 3, right: 2 })
+    );
+}
+
+#[test]
+fn test_average_hamming_distance_needs_two_blocks() {
+    assert_eq!(try_average_hamming_distance(b"abcdabce", 4), Ok(1.0));
+    assert_eq!(
+        try_average_hamming_distance(b"abcdabc", 4),
+        Err(Error::InvalidLength {
+            expected: 8,
+            actual: 7
+        })
+    );
+    assert_eq!(
+        try_average_hamming_distance(b"abcd", 0),
+        Err(Error::InvalidLength {
+            expected: 1,
+            actual: 0
+        })
+    );
+}
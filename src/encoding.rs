@@ -1,5 +1,11 @@
+use crate::error::{or_panic, Error, Result};
+
 pub fn hex_to_base64(source: &str) -> String {
-    bytes_to_base64(&hex_to_bytes(source))
+    or_panic(try_hex_to_base64(source))
+}
+
+pub fn try_hex_to_base64(source: &str) -> Result<String> {
+    Ok(bytes_to_base64(&try_hex_to_bytes(source)?))
 }
 
 pub fn block_pretty_print(bytes: &[u8]) -> String {
@@ -8,52 +14,77 @@ pub fn block_pretty_print(bytes: &[u8]) -> String {
 }
 
 pub fn hex_to_bytes(source: &str) -> Vec<u8> {
+    try_hex_to_bytes(source).unwrap_or_else(|err| panic!("Invalid hex string: {}", err))
+}
+
+// Error positions are character (not byte) offsets into `source`
+pub fn try_hex_to_bytes(source: &str) -> Result<Vec<u8>> {
     let chars: Vec<_> = source.chars().collect();
+
+    if !chars.len().is_multiple_of(2) {
+        return Err(Error::InvalidEncoding {
+            position: chars.len(),
+        });
+    }
+
     let mut bytes: Vec<u8> = Vec::with_capacity(chars.len() / 2);
+    let digit = |position: usize| {
+        hex_char_to_byte(chars[position]).ok_or(Error::InvalidEncoding { position })
+    };
 
     for index in (0..chars.len()).step_by(2) {
-        let chr1 = hex_char_to_byte(chars[index]);
-        let chr2 = hex_char_to_byte(chars[index + 1]);
-
-        let byte = (chr1 << 4) | chr2;
+        let byte = (digit(index)? << 4) | digit(index + 1)?;
         bytes.push(byte);
     }
 
-    bytes
+    Ok(bytes)
 }
 
 pub fn base64_to_bytes(source: &str) -> Vec<u8> {
-    let chars: Vec<_> = source.chars().filter(|chr| !chr.is_whitespace()).collect();
+    try_base64_to_bytes(source).unwrap_or_else(|err| panic!("Invalid Base64 string: {}", err))
+}
+
+// Whitespace is skipped, and `=` is only accepted as padding at the end of the last group.
+// Error positions are character (not byte) offsets into `source`
+pub fn try_base64_to_bytes(source: &str) -> Result<Vec<u8>> {
+    let chars: Vec<(usize, char)> = source
+        .chars()
+        .enumerate()
+        .filter(|(_, chr)| !chr.is_whitespace())
+        .collect();
     let base64len = chars.len();
 
-    if base64len % 4 != 0 {
-        panic!("Invalid Base64 string length: {}", base64len);
+    if !base64len.is_multiple_of(4) {
+        return Err(Error::InvalidEncoding {
+            position: source.chars().count(),
+        });
     }
 
-    let chr = |index| base64_char_to_byte(chars[index]);
-
-    let mut result: Vec<u8> = Vec::with_capacity(base64len * 2 / 3);
-
-    for index in (0..base64len).step_by(4) {
-        let n1 = (chr(index) << 2) | (chr(index + 1) >> 4);
-        result.push(n1);
-
-        if chars[index + 2] == '=' {
-            continue;
-        }
+    let mut result: Vec<u8> = Vec::with_capacity(base64len / 4 * 3);
 
-        let n2 = (chr(index + 1) << 4) | (chr(index + 2) >> 2);
-        result.push(n2);
+    for (group_index, group) in chars.chunks(4).enumerate() {
+        let padding = if (group_index + 1) * 4 == base64len {
+            group
+                .iter()
+                .rev()
+                .take_while(|(_, chr)| *chr == '=')
+                .count()
+                .min(2)
+        } else {
+            0
+        };
 
-        if chars[index + 3] == '=' {
-            continue;
+        let mut value: u32 = 0;
+        for &(position, chr) in &group[..4 - padding] {
+            let sextet = base64_char_to_byte(chr).ok_or(Error::InvalidEncoding { position })?;
+            value = (value << 6) | sextet as u32;
         }
+        value <<= 6 * padding;
 
-        let n3 = (chr(index + 2) << 6) | chr(index + 3);
-        result.push(n3);
+        result.extend_from_slice(&value.to_be_bytes()[1..4 - padding]);
     }
 
-    result
+    Ok(result)
 }
 
 const HEX_TABLE: &'static [char] = &[
@@ -132,22 +163,22 @@ pub fn bytes_to_base64(bytes: &[u8]) -> String {
     result
 }
 
-fn hex_char_to_byte(chr: char) -> u8 {
+fn hex_char_to_byte(chr: char) -> Option<u8> {
     match chr {
-        '0'..='9' => (chr as u8) - ('0' as u8),
-        'a'..='f' => (chr as u8) - ('a' as u8) + 10,
-        _ => panic!("Invalid character on hex string: {}", chr),
+        '0'..='9' => Some((chr as u8) - b'0'),
+        'a'..='f' => Some((chr as u8) - b'a' + 10),
+        _ => None,
     }
 }
 
-fn base64_char_to_byte(chr: char) -> u8 {
+fn base64_char_to_byte(chr: char) -> Option<u8> {
     match chr {
-        'A'..='Z' => (chr as u8) - ('A' as u8),
-        'a'..='z' => (chr as u8) - ('a' as u8) + 26,
-        '0'..='9' => (chr as u8) - ('0' as u8) + 52,
-        '+' => 62,
-        '/' => 63,
-        _ => panic!("Invalid Base64 character {:?}", chr),
+        'A'..='Z' => Some((chr as u8) - b'A'),
+        'a'..='z' => Some((chr as u8) - b'a' + 26),
+        '0'..='9' => Some((chr as u8) - b'0' + 52),
+        '+' => Some(62),
+        '/' => Some(63),
+        _ => None,
     }
 }
 
@@ -179,3 +210,41 @@ generation of knowledge, exceeds the short vehemence of any carnal pleasure.";
 
     assert_eq!(base64_to_bytes(encoded), decoded.as_ref());
 }
+
+#[test]
+fn test_invalid_encodings_report_position() {
+    assert_eq!(try_hex_to_bytes("00ff"), Ok(vec![0, 255]));
+    assert_eq!(
+        try_hex_to_bytes("00fg"),
+        Err(Error::InvalidEncoding { position: 3 })
+    );
+    assert_eq!(
+        try_hex_to_bytes("abc"),
+        Err(Error::InvalidEncoding { position: 3 })
+    );
+    assert_eq!(
+        try_hex_to_base64("49276dz"),
+        Err(Error::InvalidEncoding { position: 7 })
+    );
+    assert_eq!(
+        try_hex_to_base64("49276x"),
+        Err(Error::InvalidEncoding { position: 5 })
+    );
+
+    assert_eq!(
+        try_base64_to_bytes("TW\nF*"),
+        Err(Error::InvalidEncoding { position: 4 })
+    );
+    assert_eq!(
+        try_base64_to_bytes("T==="),
+        Err(Error::InvalidEncoding { position: 1 })
+    );
+    assert_eq!(
+        try_base64_to_bytes("TQ==TWFu"),
+        Err(Error::InvalidEncoding { position: 2 })
+    );
+    assert_eq!(
+        try_base64_to_bytes("TWF"),
+        Err(Error::InvalidEncoding { position: 3 })
+    );
+}
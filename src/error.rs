@@ -0,0 +1,64 @@
+use std::fmt;
+use std::io;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    BadPadding,
+    BadTag,
+    IntegrityCheckFailed,
+    InvalidEncoding { position: usize },
+    InvalidLength { expected: usize, actual: usize },
+    LengthMismatch { left: usize, right: usize },
+    NotBlockAligned { block_size: usize, length: usize },
+    NotInvertible,
+    Backend(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::BadPadding => write!(f, "Bad Padding"),
+            Error::BadTag => write!(f, "Bad Tag"),
+            Error::IntegrityCheckFailed => write!(f, "Integrity check failed"),
+            Error::InvalidEncoding { position } => {
+                write!(f, "Invalid encoding at position {}", position)
+            }
+            Error::InvalidLength { expected, actual } => {
+                write!(f, "Invalid length: expected {}, got {}", expected, actual)
+            }
+            Error::LengthMismatch { left, right } => {
+                write!(f, "Lengths don't match: {} vs {}", left, right)
+            }
+            Error::NotBlockAligned { block_size, length } => write!(
+                f,
+                "Length must be a multiple of {} bytes (got {})",
+                block_size, length
+            ),
+            Error::NotInvertible => write!(f, "Value isn't invertible"),
+            Error::Backend(message) => write!(f, "Backend error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// The infallible variants of most functions are thin wrappers over the `try_` ones, for callers
+// that control their inputs and would rather not deal with errors
+pub(crate) fn or_panic<T>(result: Result<T>) -> T {
+    result.unwrap_or_else(|err| panic!("{}", err))
+}
+
+impl From<openssl::error::ErrorStack> for Error {
+    fn from(error: openssl::error::ErrorStack) -> Self {
+        Error::Backend(error.to_string())
+    }
+}
+
+// Lets the streaming adapters surface crate errors through `Read` and `Write`
+impl From<Error> for io::Error {
+    fn from(error: Error) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
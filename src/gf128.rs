@@ -0,0 +1,478 @@
+use std::convert::TryInto;
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Rem, Sub};
+
+use rand::prelude::*;
+
+// Elements use GCM's bit ordering: the most significant bit of the first byte is the
+// coefficient of x^0, so the polynomial x is 0x40 followed by 15 zero bytes
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Gf128(u128);
+
+// x^128 + x^7 + x^2 + x + 1, minus the x^128 term, in the reflected bit order
+const R: u128 = 0xe1 << 120;
+
+impl Gf128 {
+    pub const ZERO: Gf128 = Gf128(0);
+    pub const ONE: Gf128 = Gf128(1 << 127);
+    pub const X: Gf128 = Gf128(1 << 126);
+
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let bytes: [u8; 16] = bytes
+            .try_into()
+            .unwrap_or_else(|_| panic!("GF(2^128) elements have 16 bytes (got {})", bytes.len()));
+
+        Gf128(u128::from_be_bytes(bytes))
+    }
+
+    pub fn to_bytes(self) -> [u8; 16] {
+        self.0.to_be_bytes()
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn random<R: Rng>(rng: &mut R) -> Self {
+        Gf128(rng.gen())
+    }
+
+    pub fn square(self) -> Self {
+        self * self
+    }
+
+    pub fn pow(self, mut exponent: u128) -> Self {
+        let mut result = Gf128::ONE;
+        let mut base = self;
+
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result *= base;
+            }
+
+            base = base.square();
+            exponent >>= 1;
+        }
+
+        result
+    }
+
+    // a^-1 = a^(2^128 - 2) = a^2 * a^4 * ... * a^(2^127)
+    pub fn inverse(self) -> Option<Self> {
+        if self.is_zero() {
+            return None;
+        }
+
+        let mut result = Gf128::ONE;
+        let mut power = self;
+
+        for _ in 1..128 {
+            power = power.square();
+            result *= power;
+        }
+
+        Some(result)
+    }
+
+    // Squaring is a bijection in characteristic 2, so every element has exactly one square root
+    pub fn sqrt(self) -> Self {
+        let mut result = self;
+        for _ in 0..127 {
+            result = result.square();
+        }
+
+        result
+    }
+}
+
+impl fmt::Debug for Gf128 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Gf128({:032x})", self.0)
+    }
+}
+
+impl Add for Gf128 {
+    type Output = Gf128;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn add(self, other: Gf128) -> Gf128 {
+        Gf128(self.0 ^ other.0)
+    }
+}
+
+impl AddAssign for Gf128 {
+    #[allow(clippy::suspicious_op_assign_impl)]
+    fn add_assign(&mut self, other: Gf128) {
+        self.0 ^= other.0;
+    }
+}
+
+impl Sub for Gf128 {
+    type Output = Gf128;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn sub(self, other: Gf128) -> Gf128 {
+        Gf128(self.0 ^ other.0)
+    }
+}
+
+impl Mul for Gf128 {
+    type Output = Gf128;
+
+    fn mul(self, other: Gf128) -> Gf128 {
+        let mut result = 0;
+        let mut v = other.0;
+
+        for bit in (0..128).rev() {
+            if (self.0 >> bit) & 1 == 1 {
+                result ^= v;
+            }
+
+            v = if v & 1 == 1 { (v >> 1) ^ R } else { v >> 1 };
+        }
+
+        Gf128(result)
+    }
+}
+
+impl MulAssign for Gf128 {
+    fn mul_assign(&mut self, other: Gf128) {
+        *self = *self * other;
+    }
+}
+
+impl Div for Gf128 {
+    type Output = Gf128;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, other: Gf128) -> Gf128 {
+        self * other.inverse().expect("Division by zero in GF(2^128)")
+    }
+}
+
+// Polynomials with coefficients on GF(2^128), stored from the lowest degree up and kept without
+// trailing zeroes (so the zero polynomial has no coefficients at all)
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Polynomial {
+    coefficients: Vec<Gf128>,
+}
+
+impl Polynomial {
+    pub fn new(coefficients: Vec<Gf128>) -> Self {
+        let mut polynomial = Self { coefficients };
+        polynomial.normalize();
+        polynomial
+    }
+
+    pub fn zero() -> Self {
+        Self::new(vec![])
+    }
+
+    pub fn one() -> Self {
+        Self::new(vec![Gf128::ONE])
+    }
+
+    pub fn monomial(coefficient: Gf128, degree: usize) -> Self {
+        let mut coefficients = vec![Gf128::ZERO; degree + 1];
+        coefficients[degree] = coefficient;
+
+        Self::new(coefficients)
+    }
+
+    pub fn coefficients(&self) -> &[Gf128] {
+        &self.coefficients
+    }
+
+    pub fn coefficient(&self, degree: usize) -> Gf128 {
+        self.coefficients
+            .get(degree)
+            .copied()
+            .unwrap_or(Gf128::ZERO)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coefficients.is_empty()
+    }
+
+    pub fn degree(&self) -> Option<usize> {
+        self.coefficients.len().checked_sub(1)
+    }
+
+    pub fn leading_coefficient(&self) -> Gf128 {
+        self.coefficients.last().copied().unwrap_or(Gf128::ZERO)
+    }
+
+    pub fn evaluate(&self, x: Gf128) -> Gf128 {
+        self.coefficients
+            .iter()
+            .rev()
+            .fold(Gf128::ZERO, |acc, &coefficient| acc * x + coefficient)
+    }
+
+    pub fn monic(&self) -> Self {
+        if self.is_zero() {
+            return self.clone();
+        }
+
+        let inverse = self.leading_coefficient().inverse().unwrap();
+        self.scale(inverse)
+    }
+
+    pub fn scale(&self, factor: Gf128) -> Self {
+        Self::new(self.coefficients.iter().map(|&c| c * factor).collect())
+    }
+
+    pub fn derivative(&self) -> Self {
+        // i * c is c when i is odd and zero when it's even, in characteristic 2
+        let coefficients = self
+            .coefficients
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, &c)| if i % 2 == 1 { c } else { Gf128::ZERO })
+            .collect();
+
+        Self::new(coefficients)
+    }
+
+    pub fn div_rem(&self, divisor: &Polynomial) -> (Polynomial, Polynomial) {
+        let divisor_degree = divisor.degree().expect("Polynomial division by zero");
+        let leading_inverse = divisor.leading_coefficient().inverse().unwrap();
+
+        let mut remainder = self.coefficients.clone();
+        let quotient_len = (remainder.len() + 1).saturating_sub(divisor.coefficients.len());
+        let mut quotient = vec![Gf128::ZERO; quotient_len];
+
+        for shift in (0..quotient_len).rev() {
+            let factor = remainder[shift + divisor_degree] * leading_inverse;
+            if factor.is_zero() {
+                continue;
+            }
+
+            quotient[shift] = factor;
+            for (i, &c) in divisor.coefficients.iter().enumerate() {
+                remainder[shift + i] += c * factor;
+            }
+        }
+
+        (Polynomial::new(quotient), Polynomial::new(remainder))
+    }
+
+    pub fn gcd(&self, other: &Polynomial) -> Polynomial {
+        let mut a = self.clone();
+        let mut b = other.clone();
+
+        while !b.is_zero() {
+            let remainder = &a % &b;
+            a = b;
+            b = remainder;
+        }
+
+        a.monic()
+    }
+
+    pub fn mul_mod(&self, other: &Polynomial, modulus: &Polynomial) -> Polynomial {
+        &(self * other) % modulus
+    }
+
+    pub fn pow_mod(&self, mut exponent: u128, modulus: &Polynomial) -> Polynomial {
+        let mut result = &Polynomial::one() % modulus;
+        let mut base = self % modulus;
+
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.mul_mod(&base, modulus);
+            }
+
+            base = base.mul_mod(&base, modulus);
+            exponent >>= 1;
+        }
+
+        result
+    }
+
+    // All distinct roots on GF(2^128), found by isolating the product of the linear factors with
+    // gcd(f, x^(2^128) - x) and then splitting it with Cantor-Zassenhaus (using the trace map,
+    // since the usual (q - 1) / 2 exponent doesn't exist on characteristic 2)
+    pub fn roots(&self) -> Vec<Gf128> {
+        if self.degree().unwrap_or(0) == 0 {
+            return vec![];
+        }
+
+        let f = self.monic();
+        let x = Polynomial::monomial(Gf128::ONE, 1);
+
+        let mut x_to_q = &x % &f;
+        for _ in 0..128 {
+            x_to_q = x_to_q.mul_mod(&x_to_q, &f);
+        }
+
+        let linear_part = f.gcd(&(&x_to_q - &x));
+
+        let mut roots = Vec::new();
+        split_linear_factors(linear_part, &mut thread_rng(), &mut roots);
+        roots
+    }
+
+    fn normalize(&mut self) {
+        while self.coefficients.last().is_some_and(|c| c.is_zero()) {
+            self.coefficients.pop();
+        }
+    }
+}
+
+fn split_linear_factors<R: Rng>(f: Polynomial, rng: &mut R, roots: &mut Vec<Gf128>) {
+    match f.degree() {
+        None | Some(0) => return,
+        Some(1) => {
+            // monic, so f = x + c and the root is c
+            roots.push(f.coefficient(0));
+            return;
+        }
+        _ => {}
+    }
+
+    loop {
+        let a = Polynomial::monomial(Gf128::random(rng), 1);
+
+        let mut term = &a % &f;
+        let mut trace = term.clone();
+        for _ in 1..128 {
+            term = term.mul_mod(&term, &f);
+            trace = &trace + &term;
+        }
+
+        let factor = f.gcd(&trace);
+        let factor_degree = factor.degree().unwrap_or(0);
+
+        if factor_degree > 0 && factor_degree < f.degree().unwrap() {
+            let (cofactor, _) = f.div_rem(&factor);
+
+            split_linear_factors(factor, rng, roots);
+            split_linear_factors(cofactor.monic(), rng, roots);
+            return;
+        }
+    }
+}
+
+impl<'a> Add for &'a Polynomial {
+    type Output = Polynomial;
+
+    fn add(self, other: &'a Polynomial) -> Polynomial {
+        let len = self.coefficients.len().max(other.coefficients.len());
+        let coefficients = (0..len)
+            .map(|i| self.coefficient(i) + other.coefficient(i))
+            .collect();
+
+        Polynomial::new(coefficients)
+    }
+}
+
+impl<'a> Sub for &'a Polynomial {
+    type Output = Polynomial;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn sub(self, other: &'a Polynomial) -> Polynomial {
+        self + other
+    }
+}
+
+impl<'a> Mul for &'a Polynomial {
+    type Output = Polynomial;
+
+    fn mul(self, other: &'a Polynomial) -> Polynomial {
+        if self.is_zero() || other.is_zero() {
+            return Polynomial::zero();
+        }
+
+        let mut coefficients =
+            vec![Gf128::ZERO; self.coefficients.len() + other.coefficients.len() - 1];
+
+        for (i, &a) in self.coefficients.iter().enumerate() {
+            for (j, &b) in other.coefficients.iter().enumerate() {
+                coefficients[i + j] += a * b;
+            }
+        }
+
+        Polynomial::new(coefficients)
+    }
+}
+
+impl<'a> Div for &'a Polynomial {
+    type Output = Polynomial;
+
+    fn div(self, other: &'a Polynomial) -> Polynomial {
+        self.div_rem(other).0
+    }
+}
+
+impl<'a> Rem for &'a Polynomial {
+    type Output = Polynomial;
+
+    fn rem(self, other: &'a Polynomial) -> Polynomial {
+        self.div_rem(other).1
+    }
+}
+
+#[test]
+fn test_field_arithmetic() {
+    let mut rng = thread_rng();
+    let a = Gf128::random(&mut rng);
+    let b = Gf128::random(&mut rng);
+
+    assert_eq!(a * Gf128::ONE, a);
+    assert_eq!(a * b, b * a);
+    assert_eq!(a + a, Gf128::ZERO);
+    assert_eq!(a * a.inverse().unwrap(), Gf128::ONE);
+    assert_eq!((a * b) / b, a);
+    assert_eq!(a.sqrt().square(), a);
+    assert_eq!(Gf128::ZERO.inverse(), None);
+
+    // x^127 * x = x^128 = x^7 + x^2 + x + 1
+    let x_127 = Gf128::from_bytes(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+    assert_eq!(x_127 * Gf128::X, Gf128(R));
+}
+
+#[test]
+fn test_polynomial_division() {
+    let mut rng = thread_rng();
+    let random_polynomial = |degree: usize, rng: &mut ThreadRng| {
+        Polynomial::new((0..=degree).map(|_| Gf128::random(rng)).collect())
+    };
+
+    let a = random_polynomial(7, &mut rng);
+    let b = random_polynomial(3, &mut rng);
+
+    let (quotient, remainder) = a.div_rem(&b);
+    assert_eq!(quotient.degree(), Some(4));
+    assert!(remainder.degree().unwrap() < 3);
+    assert_eq!(&(&quotient * &b) + &remainder, a);
+
+    let common = random_polynomial(2, &mut rng);
+    assert_eq!((&a * &common).gcd(&(&b * &common)), common.monic());
+}
+
+#[test]
+fn test_polynomial_roots() {
+    let mut rng = thread_rng();
+    let expected: Vec<Gf128> = (0..4).map(|_| Gf128::random(&mut rng)).collect();
+
+    // (x - r0)(x - r1)(x - r2)(x - r3) * an irreducible quadratic, which has no roots
+    let mut f = Polynomial::new(vec![Gf128::X, Gf128::ONE, Gf128::ONE]);
+    while !f.roots().is_empty() {
+        f = Polynomial::new(vec![Gf128::random(&mut rng), Gf128::ONE, Gf128::ONE]);
+    }
+
+    for &root in &expected {
+        f = &f * &Polynomial::new(vec![root, Gf128::ONE]);
+    }
+
+    let mut roots = f.roots();
+    roots.sort_by_key(|root| root.0);
+
+    let mut expected = expected;
+    expected.sort_by_key(|root| root.0);
+
+    assert_eq!(roots, expected);
+    assert!(roots.iter().all(|&root| f.evaluate(root).is_zero()));
+}
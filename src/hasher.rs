@@ -0,0 +1,138 @@
+use std::convert::TryInto;
+
+// Streaming hashes, which is all HMAC, HKDF and the generic collision searches need
+pub trait Hasher: Sized {
+    const BLOCK_SIZE: usize;
+    const OUTPUT_SIZE: usize;
+
+    fn new() -> Self;
+    fn update(&mut self, message: &[u8]);
+    fn finalize(self) -> Vec<u8>;
+
+    fn digest(message: &[u8]) -> Vec<u8> {
+        let mut hasher = Self::new();
+        hasher.update(message);
+        hasher.finalize()
+    }
+}
+
+// Merkle–Damgård style hashes, with enough of their internals exposed that attacks (length
+// extension, multicollisions, second preimages, herding) can be written once for all of them.
+// Chaining `compress` over the padded message has to give the same result as `digest`, which rules
+// out hashes like BLAKE2 that treat the last block differently.
+//
+// The registers are the chaining value, serialized the same way the digest is. For the hashes
+// whose digest is the whole state, that means a digest can be fed straight back in, and for the
+// truncated ones the digest is a prefix of the final registers
+pub trait MerkleDamgard: Hasher {
+    // Resumes from exported registers, as if `processed_bits` had already gone through them
+    fn from_registers(registers: &[u8], processed_bits: u64) -> Self;
+
+    // The chaining value after the last full block. Buffered bytes aren't included
+    fn registers(&self) -> Vec<u8>;
+
+    // What `finalize` appends to a message of this many bytes
+    fn padding(message_len: usize) -> Vec<u8>;
+
+    // A single application of the compression function
+    fn compress(registers: &[u8], block: &[u8]) -> Vec<u8> {
+        assert_eq!(block.len(), Self::BLOCK_SIZE);
+
+        let mut hasher = Self::from_registers(registers, 0);
+        hasher.update(block);
+        hasher.registers()
+    }
+}
+
+pub(crate) fn be_u32_words(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks(4)
+        .map(|word| u32::from_be_bytes(word.try_into().unwrap()))
+        .collect()
+}
+
+pub(crate) fn le_u32_words(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks(4)
+        .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+        .collect()
+}
+
+pub(crate) fn be_u64_words(bytes: &[u8]) -> Vec<u64> {
+    bytes
+        .chunks(8)
+        .map(|word| u64::from_be_bytes(word.try_into().unwrap()))
+        .collect()
+}
+
+#[cfg(test)]
+use crate::{
+    blake2::{Blake2b512, Blake2s256},
+    md4::MD4,
+    md5::MD5,
+    sha1::SHA1,
+    sha256::SHA256,
+    sha3::{SHA3_224, SHA3_256, SHA3_384, SHA3_512},
+    sha512::{SHA384, SHA512, SHA512_256},
+    toy_hash::{ToyHash16, ToyHash32},
+};
+
+#[cfg(test)]
+fn check_streaming<H: Hasher>() {
+    let message: Vec<u8> = (0..=255).collect();
+    let digest = H::digest(&message);
+    assert_eq!(digest.len(), H::OUTPUT_SIZE);
+
+    let mut hasher = H::new();
+    for chunk in message.chunks(7) {
+        hasher.update(chunk);
+    }
+    assert_eq!(hasher.finalize(), digest);
+}
+
+#[cfg(test)]
+fn check_hasher<H: MerkleDamgard>() {
+    check_streaming::<H>();
+
+    let message: Vec<u8> = (0..=255).collect();
+    let digest = H::digest(&message);
+
+    // Running the padded message through the compression function by hand gives the same digest
+    let mut padded = message.clone();
+    padded.extend(H::padding(message.len()));
+    assert_eq!(padded.len() % H::BLOCK_SIZE, 0);
+
+    let registers = padded
+        .chunks(H::BLOCK_SIZE)
+        .fold(H::new().registers(), |registers, block| {
+            H::compress(&registers, block)
+        });
+    assert_eq!(&registers[..H::OUTPUT_SIZE], &digest[..]);
+
+    // Resuming from the registers after the first block only needs the rest of the message
+    let first_block = H::compress(&H::new().registers(), &message[..H::BLOCK_SIZE]);
+    let mut resumed = H::from_registers(&first_block, (H::BLOCK_SIZE * 8) as u64);
+    resumed.update(&message[H::BLOCK_SIZE..]);
+    assert_eq!(resumed.finalize(), digest);
+}
+
+#[test]
+fn test_hashers_agree_with_their_compression_functions() {
+    check_hasher::<MD4>();
+    check_hasher::<MD5>();
+    check_hasher::<SHA1>();
+    check_hasher::<SHA256>();
+    check_hasher::<SHA384>();
+    check_hasher::<SHA512>();
+    check_hasher::<SHA512_256>();
+    check_hasher::<SHA3_224>();
+    check_hasher::<SHA3_256>();
+    check_hasher::<SHA3_384>();
+    check_hasher::<SHA3_512>();
+    check_hasher::<ToyHash16>();
+    check_hasher::<ToyHash32>();
+
+    // BLAKE2 flags its last block rather than padding the message, so it only streams
+    check_streaming::<Blake2b512>();
+    check_streaming::<Blake2s256>();
+}
@@ -0,0 +1,302 @@
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+
+use rand::{thread_rng, Rng};
+use rayon::prelude::*;
+
+use crate::error::{or_panic, Error, Result};
+use crate::hasher::MerkleDamgard;
+use crate::multicollision::{self, BlockCollision};
+
+// Kelsey and Kohno's diamond structure: 2^k arbitrary chaining values at the leaves, paired up and
+// collided into 2^(k - 1), and so on down to a single root. From any leaf there's a k-block path
+// to the root, so reaching any of the leaves is as good as reaching the root, and reaching one of
+// 2^k targets is 2^k times cheaper than reaching one
+pub struct DiamondStructure<H> {
+    leaves: Vec<Vec<u8>>,
+    // `levels[i][j]` merges nodes 2j and 2j + 1 of the level above it (the leaves, for i = 0)
+    levels: Vec<Vec<BlockCollision>>,
+    hasher: PhantomData<H>,
+}
+
+impl<H: MerkleDamgard> DiamondStructure<H> {
+    pub fn new(k: usize) -> Self {
+        or_panic(Self::try_new(k))
+    }
+
+    // Each level is a batch of independent collision searches, so they're spread across threads
+    pub fn try_new(k: usize) -> Result<Self> {
+        // There have to be 2^k distinct chaining values to use as leaves, and 2^k has to fit in
+        // a usize
+        let registers_len = H::new().registers().len();
+        let max_k = (8 * registers_len).min(usize::BITS as usize - 1);
+        if k > max_k {
+            return Err(Error::InvalidLength {
+                expected: max_k,
+                actual: k,
+            });
+        }
+
+        let mut rng = thread_rng();
+
+        // The leaves need to be distinct, or there's nothing to collide
+        let mut leaves = HashSet::with_capacity(1 << k);
+        while leaves.len() < 1 << k {
+            let mut leaf = vec![0; registers_len];
+            rng.fill(&mut leaf[..]);
+            leaves.insert(leaf);
+        }
+        let leaves: Vec<_> = leaves.into_iter().collect();
+
+        let mut levels: Vec<Vec<BlockCollision>> = Vec::with_capacity(k);
+        let mut nodes = leaves.clone();
+
+        for _ in 0..k {
+            let find_collision = |index: usize| {
+                multicollision::find_block_collision_between::<H>(
+                    &nodes[2 * index],
+                    &nodes[2 * index + 1],
+                )
+            };
+
+            let mut level: Vec<_> = (0..nodes.len() / 2)
+                .into_par_iter()
+                .map(find_collision)
+                .collect();
+
+            // Two pairs can collide into the same chaining value, which leaves the next level
+            // with nothing to collide for them. With a small state that's common, so redo the
+            // later pair until every node on the level is distinct
+            loop {
+                let mut seen = HashSet::with_capacity(level.len());
+                let duplicates: Vec<_> = (0..level.len())
+                    .filter(|&index| !seen.insert(&level[index].registers))
+                    .collect();
+
+                if duplicates.is_empty() {
+                    break;
+                }
+
+                let redone: Vec<_> = duplicates
+                    .par_iter()
+                    .map(|&index| find_collision(index))
+                    .collect();
+                for (index, collision) in duplicates.into_iter().zip(redone) {
+                    level[index] = collision;
+                }
+            }
+
+            nodes = level
+                .iter()
+                .map(|collision| collision.registers.clone())
+                .collect();
+            levels.push(level);
+        }
+
+        Ok(Self {
+            leaves,
+            levels,
+            hasher: PhantomData,
+        })
+    }
+
+    pub fn depth(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn leaves(&self) -> &[Vec<u8>] {
+        &self.leaves
+    }
+
+    pub fn root(&self) -> &[u8] {
+        match self.levels.last() {
+            Some(level) => &level[0].registers,
+            None => &self.leaves[0],
+        }
+    }
+
+    // The k blocks that lead from a leaf to the root
+    pub fn path(&self, mut leaf_index: usize) -> Vec<u8> {
+        let mut path = Vec::with_capacity(self.depth() * H::BLOCK_SIZE);
+
+        for level in self.levels.iter() {
+            path.extend_from_slice(&level[leaf_index / 2].blocks[leaf_index % 2]);
+            leaf_index /= 2;
+        }
+
+        path
+    }
+
+    // A block that takes `registers` to one of the leaves, and which leaf that is. Expect around
+    // 2^n / 2^k tries for an n-bit chaining value
+    pub fn find_link(&self, registers: &[u8]) -> (Vec<u8>, usize) {
+        let leaves: HashMap<_, _> = self
+            .leaves
+            .iter()
+            .enumerate()
+            .map(|(index, leaf)| (leaf, index))
+            .collect();
+
+        rayon::iter::repeat(())
+            .map_init(thread_rng, |rng, _| {
+                let mut block = vec![0; H::BLOCK_SIZE];
+                rng.fill(&mut block[..]);
+                block
+            })
+            .find_map_any(|block| {
+                let next_registers = H::compress(registers, &block);
+                leaves
+                    .get(&next_registers)
+                    .map(|&leaf_index| (block, leaf_index))
+            })
+            .unwrap()
+    }
+}
+
+// The Nostradamus attack: commit to a hash now, and later produce a message with whatever prefix
+// turns out to be convenient that hashes to it. The commitment is the digest of a message of a
+// fixed length that ends at the root of a diamond structure, and a prefix gets there through a
+// link block into one of the leaves and the path from that leaf
+pub struct Prediction<H> {
+    diamond: DiamondStructure<H>,
+    prefix_blocks: usize,
+    digest: Vec<u8>,
+}
+
+impl<H: MerkleDamgard> Prediction<H> {
+    pub fn new(k: usize, prefix_len: usize) -> Self {
+        or_panic(Self::try_new(k, prefix_len))
+    }
+
+    pub fn try_new(k: usize, prefix_len: usize) -> Result<Self> {
+        let diamond = DiamondStructure::<H>::try_new(k)?;
+
+        let prefix_blocks = prefix_len.div_ceil(H::BLOCK_SIZE);
+        let message_len = (prefix_blocks + 1 + k) * H::BLOCK_SIZE;
+
+        // The digest only depends on the root and the length, which is fixed up front
+        let digest = H::from_registers(diamond.root(), (message_len * 8) as u64).finalize();
+
+        Ok(Self {
+            diamond,
+            prefix_blocks,
+            digest,
+        })
+    }
+
+    pub fn digest(&self) -> &[u8] {
+        &self.digest
+    }
+
+    pub fn diamond(&self) -> &DiamondStructure<H> {
+        &self.diamond
+    }
+
+    // The longest prefix that fits. Shorter ones get padded with spaces
+    pub fn prefix_len(&self) -> usize {
+        self.prefix_blocks * H::BLOCK_SIZE
+    }
+
+    pub fn message_len(&self) -> usize {
+        self.prefix_len() + (1 + self.diamond.depth()) * H::BLOCK_SIZE
+    }
+
+    pub fn herd(&self, prefix: &[u8]) -> Vec<u8> {
+        or_panic(self.try_herd(prefix))
+    }
+
+    pub fn try_herd(&self, prefix: &[u8]) -> Result<Vec<u8>> {
+        if prefix.len() > self.prefix_len() {
+            return Err(Error::InvalidLength {
+                expected: self.prefix_len(),
+                actual: prefix.len(),
+            });
+        }
+
+        let mut message = prefix.to_vec();
+        message.resize(self.prefix_len(), b' ');
+
+        let registers = message
+            .chunks(H::BLOCK_SIZE)
+            .fold(H::new().registers(), |registers, block| {
+                H::compress(&registers, block)
+            });
+
+        let (link, leaf_index) = self.diamond.find_link(&registers);
+        message.extend_from_slice(&link);
+        message.extend_from_slice(&self.diamond.path(leaf_index));
+
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+use crate::{hasher::Hasher, toy_hash::ToyHash16};
+
+#[test]
+fn test_diamond_structure() {
+    let diamond = DiamondStructure::<ToyHash16>::new(4);
+    assert_eq!(diamond.leaves().len(), 16);
+
+    for (index, leaf) in diamond.leaves().iter().enumerate() {
+        let path = diamond.path(index);
+        assert_eq!(path.len(), 4 * ToyHash16::BLOCK_SIZE);
+
+        let root = path
+            .chunks(ToyHash16::BLOCK_SIZE)
+            .fold(leaf.clone(), |registers, block| {
+                ToyHash16::compress(&registers, block)
+            });
+        assert_eq!(root, diamond.root());
+    }
+
+    // Big enough that pairs on the first level now and then merge into the same node
+    let diamond = DiamondStructure::<ToyHash16>::new(8);
+    for index in (0..1 << 8).step_by(17) {
+        let root = diamond
+            .path(index)
+            .chunks(ToyHash16::BLOCK_SIZE)
+            .fold(diamond.leaves()[index].clone(), |registers, block| {
+                ToyHash16::compress(&registers, block)
+            });
+        assert_eq!(root, diamond.root());
+    }
+
+    // ToyHash16 doesn't have 2^17 chaining values to pick leaves from
+    assert_eq!(
+        DiamondStructure::<ToyHash16>::try_new(17).err(),
+        Some(Error::InvalidLength {
+            expected: 16,
+            actual: 17
+        })
+    );
+}
+
+#[test]
+fn test_herding() {
+    let prediction = Prediction::<ToyHash16>::new(4, 40);
+    assert_eq!(prediction.prefix_len(), 48);
+    assert_eq!(prediction.message_len(), 48 + 5 * 16);
+
+    for prefix in [&b"Heads"[..], &b"Tails, and it landed on its side too"[..]].iter() {
+        let message = prediction.herd(prefix);
+        assert!(message.starts_with(prefix));
+        assert_eq!(message.len(), prediction.message_len());
+        assert_eq!(ToyHash16::digest(&message), prediction.digest());
+    }
+
+    assert_eq!(
+        Prediction::<ToyHash16>::try_new(17, 40).err(),
+        Some(Error::InvalidLength {
+            expected: 16,
+            actual: 17
+        })
+    );
+    assert_eq!(
+        prediction.try_herd(&[b' '; 49]),
+        Err(Error::InvalidLength {
+            expected: 48,
+            actual: 49
+        })
+    );
+}
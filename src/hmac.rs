@@ -1,29 +1,82 @@
 use std::cmp::Ordering;
 
-use crate::{sha1, sha256, xor};
+use crate::bytes::constant_time_eq;
+use crate::hasher::Hasher;
+use crate::{md4, md5, sha1, sha256, xor};
 
-pub trait HashFunction {
-    const BLOCK_SIZE: usize;
+// One-shot hashing, for any `Hasher`
+pub trait HashFunction: Hasher {
+    fn compute(message: &[u8]) -> Vec<u8> {
+        Self::digest(message)
+    }
+}
 
-    fn compute(message: &[u8]) -> Vec<u8>;
+impl<H: Hasher> HashFunction for H {}
+
+// Streaming HMAC, keyed once and fed the message in any number of pieces
+pub struct Hmac<H> {
+    inner: H,
+    o_key_pad: Vec<u8>,
 }
 
-impl HashFunction for sha1::SHA1 {
-    const BLOCK_SIZE: usize = 64;
+pub type HmacMd4 = Hmac<md4::MD4>;
+pub type HmacMd5 = Hmac<md5::MD5>;
+pub type HmacSha1 = Hmac<sha1::SHA1>;
+pub type HmacSha256 = Hmac<sha256::SHA256>;
+
+impl<H: Hasher> Hmac<H> {
+    pub fn new(key: &[u8]) -> Self {
+        let derived_key = match key.len().cmp(&H::BLOCK_SIZE) {
+            Ordering::Greater => {
+                let mut hashed = H::digest(key);
+                hashed.resize(H::BLOCK_SIZE, 0_u8);
+                hashed
+            }
+            Ordering::Equal => key.to_vec(),
+
+            Ordering::Less => {
+                let mut padded = key.to_vec();
+                padded.resize(H::BLOCK_SIZE, 0_u8);
+                padded
+            }
+        };
+
+        let mut inner = H::new();
+        inner.update(&xor::rotating_xor(&derived_key, &[0x36]));
+
+        Self {
+            inner,
+            o_key_pad: xor::rotating_xor(&derived_key, &[0x5c]),
+        }
+    }
 
-    fn compute(message: &[u8]) -> Vec<u8> {
-        sha1::sha1(message)
+    pub fn update(&mut self, message: &[u8]) {
+        self.inner.update(message);
     }
-}
 
-impl HashFunction for sha256::SHA256 {
-    const BLOCK_SIZE: usize = 64;
+    pub fn finalize(self) -> Vec<u8> {
+        let inner_hash = self.inner.finalize();
 
-    fn compute(message: &[u8]) -> Vec<u8> {
-        sha256::sha256(message)
+        let mut outer = H::new();
+        outer.update(&self.o_key_pad);
+        outer.update(&inner_hash);
+        outer.finalize()
+    }
+
+    // Takes the same time wherever the tags differ, unlike comparing them with `==`
+    pub fn verify(self, tag: &[u8]) -> bool {
+        constant_time_eq(&self.finalize(), tag)
     }
 }
 
+pub fn hmac_md4(key: &[u8], message: &[u8]) -> Vec<u8> {
+    hmac::<md4::MD4>(key, message)
+}
+
+pub fn hmac_md5(key: &[u8], message: &[u8]) -> Vec<u8> {
+    hmac::<md5::MD5>(key, message)
+}
+
 pub fn hmac_sha1(key: &[u8], message: &[u8]) -> Vec<u8> {
     hmac::<sha1::SHA1>(key, message)
 }
@@ -32,27 +85,10 @@ pub fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
     hmac::<sha256::SHA256>(key, message)
 }
 
-pub fn hmac<H: HashFunction>(key: &[u8], message: &[u8]) -> Vec<u8> {
-    let derived_key = match key.len().cmp(&H::BLOCK_SIZE) {
-        Ordering::Greater => H::compute(key),
-        Ordering::Equal => key.to_vec(),
-
-        Ordering::Less => {
-            let mut padded = key.to_vec();
-            padded.resize(H::BLOCK_SIZE, 0_u8);
-            padded
-        }
-    };
-
-    let mut o_key_pad = xor::rotating_xor(&derived_key, &[0x5c]);
-    let mut i_key_pad = xor::rotating_xor(&derived_key, &[0x36]);
-
-    i_key_pad.extend_from_slice(message);
-    let inner_hash = H::compute(&i_key_pad);
-
-    o_key_pad.extend_from_slice(&inner_hash);
-
-    H::compute(&o_key_pad)
+pub fn hmac<H: Hasher>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut hmac = Hmac::<H>::new(key);
+    hmac.update(message);
+    hmac.finalize()
 }
 
 #[cfg(test)]
@@ -65,3 +101,61 @@ fn test_hmac_sha1() {
 
     assert_eq!(hex_result, "de7c9b85b8b78aa6bc8a7a36f70a90701c9db4d9");
 }
+
+#[test]
+fn test_hmac_md5() {
+    // RFC 2104 test vectors
+    let result = hmac_md5(&[0x0b; 16], b"Hi There");
+    assert_eq!(bytes_to_hex(&result), "9294727a3638bb1c13f48ef8158bfc9d");
+
+    let result = hmac_md5(b"Jefe", b"what do ya want for nothing?");
+    assert_eq!(bytes_to_hex(&result), "750c783e6ab0b503eaa86e310a5db738");
+}
+
+#[test]
+fn test_hmac_long_keys_are_hashed_and_padded() {
+    // RFC 2202 test case 6
+    let result = hmac_sha1(
+        &[0xaa; 80],
+        b"Test Using Larger Than Block-Size Key - Hash Key First",
+    );
+
+    assert_eq!(
+        bytes_to_hex(&result),
+        "aa4ae5e15272d00e95705637ce8a3b55ed402112"
+    );
+}
+
+#[test]
+fn test_hmac_streaming_and_verify() {
+    let message = b"The quick brown fox jumps over the lazy dog";
+    let expected = hmac_sha256(b"key", message);
+    assert_eq!(
+        bytes_to_hex(&expected),
+        "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+    );
+
+    let mut hmac = HmacSha256::new(b"key");
+    for chunk in message.chunks(10) {
+        hmac.update(chunk);
+    }
+    assert_eq!(hmac.finalize(), expected);
+
+    let mut hmac = HmacSha256::new(b"key");
+    hmac.update(message);
+    assert!(hmac.verify(&expected));
+
+    let mut tampered = expected.clone();
+    tampered[31] ^= 1;
+    let mut hmac = HmacSha256::new(b"key");
+    hmac.update(message);
+    assert!(!hmac.verify(&tampered));
+
+    let mut hmac = HmacSha256::new(b"key");
+    hmac.update(message);
+    assert!(!hmac.verify(&expected[..16]));
+
+    let mut hmac = HmacMd4::new(b"key");
+    hmac.update(message);
+    assert_eq!(hmac.finalize(), hmac_md4(b"key", message));
+}
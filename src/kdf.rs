@@ -0,0 +1,254 @@
+use crate::error::{or_panic, Error, Result};
+use crate::hmac::{HashFunction, Hmac};
+
+// HKDF (RFC 5869): extract concentrates whatever entropy the input keying material has into a
+// pseudorandom key, and expand stretches that into as many independent keys as needed, one per
+// `info` label
+pub fn hkdf<H: HashFunction>(salt: &[u8], ikm: &[u8], info: &[u8], len: usize) -> Vec<u8> {
+    hkdf_expand::<H>(&hkdf_extract::<H>(salt, ikm), info, len)
+}
+
+pub fn try_hkdf<H: HashFunction>(
+    salt: &[u8],
+    ikm: &[u8],
+    info: &[u8],
+    len: usize,
+) -> Result<Vec<u8>> {
+    try_hkdf_expand::<H>(&hkdf_extract::<H>(salt, ikm), info, len)
+}
+
+// No salt is the same as a salt of zeros as long as the hash output
+pub fn hkdf_extract<H: HashFunction>(salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+    let zero_salt = vec![0; H::OUTPUT_SIZE];
+    let salt = if salt.is_empty() { &zero_salt } else { salt };
+
+    let mut hmac = Hmac::<H>::new(salt);
+    hmac.update(ikm);
+    hmac.finalize()
+}
+
+pub fn hkdf_expand<H: HashFunction>(prk: &[u8], info: &[u8], len: usize) -> Vec<u8> {
+    or_panic(try_hkdf_expand::<H>(prk, info, len))
+}
+
+// The block counter is a single byte, which caps the output at 255 hash outputs
+pub fn try_hkdf_expand<H: HashFunction>(prk: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>> {
+    let max_len = 255 * H::OUTPUT_SIZE;
+    if len > max_len {
+        return Err(Error::InvalidLength {
+            expected: max_len,
+            actual: len,
+        });
+    }
+
+    let mut okm = Vec::with_capacity(len);
+    let mut previous = Vec::new();
+
+    for counter in 1..=255_u8 {
+        if okm.len() >= len {
+            break;
+        }
+
+        let mut hmac = Hmac::<H>::new(prk);
+        hmac.update(&previous);
+        hmac.update(info);
+        hmac.update(&[counter]);
+        previous = hmac.finalize();
+
+        okm.extend_from_slice(&previous);
+    }
+
+    okm.truncate(len);
+    Ok(okm)
+}
+
+// PBKDF2 (RFC 8018) with HMAC as the PRF. Every output block costs `iterations` HMACs, which is
+// the point: it makes guessing passwords that much slower
+pub fn pbkdf2<H: HashFunction>(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    len: usize,
+) -> Vec<u8> {
+    or_panic(try_pbkdf2::<H>(password, salt, iterations, len))
+}
+
+// Needs at least one iteration, and the block counter is 32 bits, which caps the output at
+// 2^32 - 1 hash outputs
+pub fn try_pbkdf2<H: HashFunction>(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    len: usize,
+) -> Result<Vec<u8>> {
+    if iterations == 0 {
+        return Err(Error::InvalidLength {
+            expected: 1,
+            actual: 0,
+        });
+    }
+
+    let max_len = (u32::MAX as usize).saturating_mul(H::OUTPUT_SIZE);
+    if len > max_len {
+        return Err(Error::InvalidLength {
+            expected: max_len,
+            actual: len,
+        });
+    }
+
+    let mut derived = Vec::with_capacity(len);
+
+    for block_index in 1_u32.. {
+        if derived.len() >= len {
+            break;
+        }
+
+        let mut hmac = Hmac::<H>::new(password);
+        hmac.update(salt);
+        hmac.update(&block_index.to_be_bytes());
+        let mut u = hmac.finalize();
+        let mut block = u.clone();
+
+        for _ in 1..iterations {
+            let mut hmac = Hmac::<H>::new(password);
+            hmac.update(&u);
+            u = hmac.finalize();
+
+            for (byte, u_byte) in block.iter_mut().zip(&u) {
+                *byte ^= u_byte;
+            }
+        }
+
+        derived.extend_from_slice(&block);
+    }
+
+    derived.truncate(len);
+    Ok(derived)
+}
+
+#[cfg(test)]
+use crate::{
+    encoding::{bytes_to_hex, hex_to_bytes},
+    sha1::SHA1,
+    sha256::SHA256,
+};
+
+#[test]
+fn test_hkdf_rfc5869_vectors() {
+    // A.1
+    let ikm = [0x0b; 22];
+    let salt = hex_to_bytes("000102030405060708090a0b0c");
+    let info = hex_to_bytes("f0f1f2f3f4f5f6f7f8f9");
+
+    let prk = hkdf_extract::<SHA256>(&salt, &ikm);
+    assert_eq!(
+        bytes_to_hex(&prk),
+        "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5"
+    );
+    assert_eq!(
+        bytes_to_hex(&hkdf_expand::<SHA256>(&prk, &info, 42)),
+        "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf\
+         34007208d5b887185865"
+    );
+
+    // A.3, with no salt and no info
+    assert_eq!(
+        bytes_to_hex(&hkdf::<SHA256>(&[], &ikm, &[], 42)),
+        "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d\
+         9d201395faa4b61a96c8"
+    );
+
+    // A.4, with SHA-1
+    let ikm = [0x0b; 11];
+    let salt = hex_to_bytes("000102030405060708090a0b0c");
+    let info = hex_to_bytes("f0f1f2f3f4f5f6f7f8f9");
+    assert_eq!(
+        bytes_to_hex(&hkdf::<SHA1>(&salt, &ikm, &info, 42)),
+        "085a01ea1b10f36933068b56efa5ad81a4f14b822f5b091568a9cdd4f155fda2\
+         c22e422478d305f3f896"
+    );
+
+    assert_eq!(
+        try_hkdf::<SHA256>(&salt, &ikm, &info, 255 * 32 + 1),
+        Err(Error::InvalidLength {
+            expected: 255 * 32,
+            actual: 255 * 32 + 1
+        })
+    );
+}
+
+#[test]
+fn test_pbkdf2_vectors() {
+    // RFC 6070, for PBKDF2-HMAC-SHA1
+    let vectors: [(&[u8], &[u8], u32, &str); 4] = [
+        (
+            b"password",
+            b"salt",
+            1,
+            "0c60c80f961f0e71f3a9b524af6012062fe037a6",
+        ),
+        (
+            b"password",
+            b"salt",
+            2,
+            "ea6c014dc72d6f8ccd1ed92ace1d41f0d8de8957",
+        ),
+        (
+            b"password",
+            b"salt",
+            4096,
+            "4b007901b765489abead49d926f721d065a429c1",
+        ),
+        (
+            b"passwordPASSWORDpassword",
+            b"saltSALTsaltSALTsaltSALTsaltSALTsalt",
+            4096,
+            "3d2eec4fe41c849b80c8d83662c0e44a8b291a964cf2f07038",
+        ),
+    ];
+
+    for &(password, salt, iterations, expected) in vectors.iter() {
+        let derived = pbkdf2::<SHA1>(password, salt, iterations, expected.len() / 2);
+        assert_eq!(bytes_to_hex(&derived), expected);
+    }
+
+    assert_eq!(
+        bytes_to_hex(&pbkdf2::<SHA256>(b"password", b"salt", 4096, 32)),
+        "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a"
+    );
+    assert_eq!(
+        try_pbkdf2::<SHA1>(b"password", b"salt", 0, 20),
+        Err(Error::InvalidLength {
+            expected: 1,
+            actual: 0
+        })
+    );
+
+    let max_len = u32::MAX as usize * 32;
+    assert_eq!(
+        try_pbkdf2::<SHA256>(b"password", b"salt", 1, max_len + 1),
+        Err(Error::InvalidLength {
+            expected: max_len,
+            actual: max_len + 1
+        })
+    );
+}
+
+#[test]
+fn test_session_key_derivation() {
+    use crate::dh::SessionKey;
+    use rug::Integer;
+
+    let session_key = SessionKey(Integer::from(0x1234_5678_9abc_u64));
+
+    let encryption_key = session_key.encryption_key();
+    let mac_key = session_key.mac_key();
+    assert_eq!(encryption_key.len(), 16);
+    assert_eq!(mac_key.len(), 32);
+    assert_ne!(&encryption_key[..], &mac_key[..16]);
+
+    assert_eq!(
+        session_key.derive_key(b"encryption", 16),
+        hkdf::<SHA256>(&[], &session_key.to_bytes(), b"encryption", 16)
+    );
+}
@@ -0,0 +1,278 @@
+use crate::block_cipher::BlockCipher;
+use crate::bytes::constant_time_eq;
+use crate::error::{or_panic, Error, Result};
+
+const SEMIBLOCK_SIZE: usize = 8;
+const DEFAULT_IV: [u8; SEMIBLOCK_SIZE] = [0xa6; SEMIBLOCK_SIZE];
+const PADDED_IV_PREFIX: [u8; 4] = [0xa6, 0x59, 0x59, 0xa6];
+
+// AES key wrap (RFC 3394) and key wrap with padding (RFC 5649), keyed with the key-encryption
+// key. Wrapping is deterministic and adds 8 bytes, which unwrapping checks to detect tampering
+// (or the wrong KEK)
+pub struct KeyWrap<C> {
+    cipher: C,
+}
+
+impl<C: BlockCipher> KeyWrap<C> {
+    pub fn new(cipher: C) -> Self {
+        if C::BLOCK_SIZE != 2 * SEMIBLOCK_SIZE {
+            panic!("Key wrap requires a cipher with 128-bit blocks");
+        }
+
+        Self { cipher }
+    }
+
+    pub fn wrap(&self, key_data: &[u8]) -> Vec<u8> {
+        or_panic(self.try_wrap(key_data))
+    }
+
+    // RFC 3394 only wraps at least two 64-bit blocks of key data
+    pub fn try_wrap(&self, key_data: &[u8]) -> Result<Vec<u8>> {
+        check_semiblocks(key_data, 2)?;
+        Ok(self.wrap_semiblocks(DEFAULT_IV, key_data))
+    }
+
+    pub fn unwrap(&self, wrapped: &[u8]) -> Result<Vec<u8>> {
+        check_semiblocks(wrapped, 3)?;
+
+        let (iv, key_data) = self.unwrap_semiblocks(wrapped);
+        if !constant_time_eq(&iv, &DEFAULT_IV) {
+            return Err(Error::IntegrityCheckFailed);
+        }
+
+        Ok(key_data)
+    }
+
+    // RFC 5649 takes any non-empty key, zero-padding it to a multiple of 8 bytes and recording
+    // its original length in the IV
+    pub fn wrap_with_padding(&self, key_data: &[u8]) -> Vec<u8> {
+        or_panic(self.try_wrap_with_padding(key_data))
+    }
+
+    pub fn try_wrap_with_padding(&self, key_data: &[u8]) -> Result<Vec<u8>> {
+        if key_data.is_empty() || key_data.len() > u32::MAX as usize {
+            return Err(Error::InvalidLength {
+                expected: SEMIBLOCK_SIZE,
+                actual: key_data.len(),
+            });
+        }
+
+        let mut iv = [0; SEMIBLOCK_SIZE];
+        iv[..4].copy_from_slice(&PADDED_IV_PREFIX);
+        iv[4..].copy_from_slice(&(key_data.len() as u32).to_be_bytes());
+
+        let mut padded = key_data.to_vec();
+        padded.resize(round_up_to_semiblock(key_data.len()), 0);
+
+        // A single semiblock is encrypted together with the IV as one block
+        if padded.len() == SEMIBLOCK_SIZE {
+            let mut block = iv.to_vec();
+            block.extend_from_slice(&padded);
+            self.cipher.encrypt_block(&mut block);
+
+            return Ok(block);
+        }
+
+        Ok(self.wrap_semiblocks(iv, &padded))
+    }
+
+    pub fn unwrap_with_padding(&self, wrapped: &[u8]) -> Result<Vec<u8>> {
+        check_semiblocks(wrapped, 2)?;
+
+        let (iv, mut key_data) = if wrapped.len() == 2 * SEMIBLOCK_SIZE {
+            let mut block = wrapped.to_vec();
+            self.cipher.decrypt_block(&mut block);
+
+            let key_data = block.split_off(SEMIBLOCK_SIZE);
+            (block, key_data)
+        } else {
+            self.unwrap_semiblocks(wrapped)
+        };
+
+        let mut length_bytes = [0; 4];
+        length_bytes.copy_from_slice(&iv[4..]);
+        let length = u32::from_be_bytes(length_bytes) as usize;
+
+        let valid_length =
+            length <= key_data.len() && round_up_to_semiblock(length) == key_data.len();
+        let valid_padding = valid_length && key_data[length..].iter().all(|&byte| byte == 0);
+
+        if !constant_time_eq(&iv[..4], &PADDED_IV_PREFIX) || !valid_padding {
+            return Err(Error::IntegrityCheckFailed);
+        }
+
+        key_data.truncate(length);
+        Ok(key_data)
+    }
+
+    // The wrapping function W from RFC 3394 section 2.2.1, with an arbitrary initial value
+    fn wrap_semiblocks(&self, iv: [u8; SEMIBLOCK_SIZE], key_data: &[u8]) -> Vec<u8> {
+        let semiblocks = key_data.len() / SEMIBLOCK_SIZE;
+
+        let mut a = iv;
+        let mut r = key_data.to_vec();
+        let mut block = [0; 2 * SEMIBLOCK_SIZE];
+
+        for j in 0..6 {
+            for (i, semiblock) in r.chunks_mut(SEMIBLOCK_SIZE).enumerate() {
+                block[..SEMIBLOCK_SIZE].copy_from_slice(&a);
+                block[SEMIBLOCK_SIZE..].copy_from_slice(semiblock);
+                self.cipher.encrypt_block(&mut block);
+
+                let t = (semiblocks * j + i + 1) as u64;
+                a.copy_from_slice(&block[..SEMIBLOCK_SIZE]);
+                xor_counter(&mut a, t);
+                semiblock.copy_from_slice(&block[SEMIBLOCK_SIZE..]);
+            }
+        }
+
+        let mut result = a.to_vec();
+        result.extend_from_slice(&r);
+        result
+    }
+
+    // The inverse W^-1, returning the recovered initial value for the caller to check
+    fn unwrap_semiblocks(&self, wrapped: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let semiblocks = wrapped.len() / SEMIBLOCK_SIZE - 1;
+
+        let mut a = [0; SEMIBLOCK_SIZE];
+        a.copy_from_slice(&wrapped[..SEMIBLOCK_SIZE]);
+        let mut r = wrapped[SEMIBLOCK_SIZE..].to_vec();
+        let mut block = [0; 2 * SEMIBLOCK_SIZE];
+
+        for j in (0..6).rev() {
+            for (i, semiblock) in r.chunks_mut(SEMIBLOCK_SIZE).enumerate().rev() {
+                let t = (semiblocks * j + i + 1) as u64;
+                xor_counter(&mut a, t);
+
+                block[..SEMIBLOCK_SIZE].copy_from_slice(&a);
+                block[SEMIBLOCK_SIZE..].copy_from_slice(semiblock);
+                self.cipher.decrypt_block(&mut block);
+
+                a.copy_from_slice(&block[..SEMIBLOCK_SIZE]);
+                semiblock.copy_from_slice(&block[SEMIBLOCK_SIZE..]);
+            }
+        }
+
+        (a.to_vec(), r)
+    }
+}
+
+fn check_semiblocks(data: &[u8], minimum: usize) -> Result<()> {
+    if !data.len().is_multiple_of(SEMIBLOCK_SIZE) {
+        return Err(Error::NotBlockAligned {
+            block_size: SEMIBLOCK_SIZE,
+            length: data.len(),
+        });
+    }
+
+    if data.len() < minimum * SEMIBLOCK_SIZE {
+        return Err(Error::InvalidLength {
+            expected: minimum * SEMIBLOCK_SIZE,
+            actual: data.len(),
+        });
+    }
+
+    Ok(())
+}
+
+fn round_up_to_semiblock(len: usize) -> usize {
+    len.div_ceil(SEMIBLOCK_SIZE) * SEMIBLOCK_SIZE
+}
+
+fn xor_counter(a: &mut [u8; SEMIBLOCK_SIZE], t: u64) {
+    for (byte, t_byte) in a.iter_mut().zip(&t.to_be_bytes()) {
+        *byte ^= t_byte;
+    }
+}
+
+#[cfg(test)]
+use crate::{
+    aes::{Aes128, Aes192, Aes256},
+    encoding::{bytes_to_hex, hex_to_bytes},
+};
+
+#[test]
+fn test_key_wrap_rfc3394_vectors() {
+    let key_data = hex_to_bytes("00112233445566778899aabbccddeeff");
+
+    let key_wrap = KeyWrap::new(Aes128::new(&hex_to_bytes(
+        "000102030405060708090a0b0c0d0e0f",
+    )));
+    let wrapped = key_wrap.wrap(&key_data);
+    assert_eq!(
+        bytes_to_hex(&wrapped),
+        "1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5"
+    );
+    assert_eq!(key_wrap.unwrap(&wrapped), Ok(key_data.clone()));
+
+    let key_wrap = KeyWrap::new(Aes256::new(&hex_to_bytes(
+        "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+    )));
+    let mut key_data = key_data;
+    key_data.extend_from_slice(&hex_to_bytes("000102030405060708090a0b0c0d0e0f"));
+    let wrapped = key_wrap.wrap(&key_data);
+    assert_eq!(
+        bytes_to_hex(&wrapped),
+        "28c9f404c4b810f4cbccb35cfb87f8263f5786e2d80ed326cbc7f0e71a99f43b\
+         fb988b9b7a02dd21"
+    );
+    assert_eq!(key_wrap.unwrap(&wrapped), Ok(key_data));
+}
+
+#[test]
+fn test_key_wrap_with_padding_rfc5649_vectors() {
+    let key_wrap = KeyWrap::new(Aes192::new(&hex_to_bytes(
+        "5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8",
+    )));
+
+    let vectors = [
+        (
+            "c37b7e6492584340bed12207808941155068f738",
+            "138bdeaa9b8fa7fc61f97742e72248ee5ae6ae5360d1ae6a5f54f373fa543b6a",
+        ),
+        ("466f7250617369", "afbeb0f07dfbf5419200f2ccb50bb24f"),
+    ];
+
+    for &(key_data, expected) in vectors.iter() {
+        let key_data = hex_to_bytes(key_data);
+        let wrapped = key_wrap.wrap_with_padding(&key_data);
+
+        assert_eq!(bytes_to_hex(&wrapped), expected);
+        assert_eq!(key_wrap.unwrap_with_padding(&wrapped), Ok(key_data));
+    }
+}
+
+#[test]
+fn test_key_wrap_detects_tampering() {
+    use crate::dh::SessionKey;
+    use rug::Integer;
+
+    let key_wrap = KeyWrap::new(Aes128::new(b"YELLOW SUBMARINE"));
+    let session_key = SessionKey(Integer::from(0x1234_5678_9abc_u64)).to_aes_key();
+
+    let mut wrapped = key_wrap.wrap(&session_key);
+    assert_eq!(key_wrap.unwrap(&wrapped), Ok(session_key.clone()));
+
+    wrapped[10] ^= 1;
+    assert_eq!(key_wrap.unwrap(&wrapped), Err(Error::IntegrityCheckFailed));
+
+    let other_kek = KeyWrap::new(Aes128::new(b"PURPLE SUBMARINE"));
+    let wrapped = key_wrap.wrap_with_padding(&session_key[..5]);
+    assert_eq!(
+        other_kek.unwrap_with_padding(&wrapped),
+        Err(Error::IntegrityCheckFailed)
+    );
+
+    // A padded wrap isn't a valid unpadded one, even when the lengths work out
+    let wrapped = key_wrap.wrap_with_padding(&session_key);
+    assert_eq!(key_wrap.unwrap(&wrapped), Err(Error::IntegrityCheckFailed));
+
+    assert_eq!(
+        key_wrap.try_wrap(&session_key[..8]),
+        Err(Error::InvalidLength {
+            expected: 16,
+            actual: 8
+        })
+    );
+}
@@ -0,0 +1,173 @@
+use std::ops::Range;
+
+use crate::error::{or_panic, Error, Result};
+use crate::hasher::MerkleDamgard;
+
+// Length extension against secret-prefix MACs, `H(secret || message)`. Given the MAC of a message
+// and the length of the secret, the hash can pick up where it left off, as if the glue padding
+// had been part of the message, and carry on with any suffix. Only works when the digest is the
+// hash's whole state, which rules out the truncated SHA-2 variants and SHA-3.
+//
+// Returns the forged message (original message, glue padding, suffix) and its MAC
+pub fn extend<H: MerkleDamgard>(
+    original_mac: &[u8],
+    original_message: &[u8],
+    secret_len: usize,
+    suffix: &[u8],
+) -> (Vec<u8>, Vec<u8>) {
+    or_panic(try_extend::<H>(
+        original_mac,
+        original_message,
+        secret_len,
+        suffix,
+    ))
+}
+
+// Fails if the MAC isn't as long as the hash's registers, either because the hash is truncated
+// or because the MAC is
+pub fn try_extend<H: MerkleDamgard>(
+    original_mac: &[u8],
+    original_message: &[u8],
+    secret_len: usize,
+    suffix: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let registers_len = H::new().registers().len();
+    if original_mac.len() != registers_len {
+        return Err(Error::InvalidLength {
+            expected: registers_len,
+            actual: original_mac.len(),
+        });
+    }
+
+    let mut forged_message = original_message.to_vec();
+    forged_message.extend(H::padding(secret_len + original_message.len()));
+
+    let processed_bits = ((secret_len + forged_message.len()) * 8) as u64;
+    let mut hasher = H::from_registers(original_mac, processed_bits);
+    hasher.update(suffix);
+
+    forged_message.extend_from_slice(suffix);
+    Ok((forged_message, hasher.finalize()))
+}
+
+pub fn find_forgery<H, F>(
+    original_mac: &[u8],
+    original_message: &[u8],
+    secret_lens: Range<usize>,
+    suffix: &[u8],
+    is_valid: F,
+) -> Option<(Vec<u8>, Vec<u8>)>
+where
+    H: MerkleDamgard,
+    F: FnMut(&[u8], &[u8]) -> bool,
+{
+    or_panic(try_find_forgery::<H, F>(
+        original_mac,
+        original_message,
+        secret_lens,
+        suffix,
+        is_valid,
+    ))
+}
+
+// Tries `try_extend` with each candidate secret length until `is_valid` accepts the forgery
+pub fn try_find_forgery<H, F>(
+    original_mac: &[u8],
+    original_message: &[u8],
+    secret_lens: Range<usize>,
+    suffix: &[u8],
+    mut is_valid: F,
+) -> Result<Option<(Vec<u8>, Vec<u8>)>>
+where
+    H: MerkleDamgard,
+    F: FnMut(&[u8], &[u8]) -> bool,
+{
+    for secret_len in secret_lens {
+        let (forged_message, forged_mac) =
+            try_extend::<H>(original_mac, original_message, secret_len, suffix)?;
+
+        if is_valid(&forged_message, &forged_mac) {
+            return Ok(Some((forged_message, forged_mac)));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+use crate::{
+    hasher::Hasher,
+    md4::MD4,
+    md5::MD5,
+    sha1::SHA1,
+    sha256::SHA256,
+    sha3::SHA3_256,
+    sha512::{SHA384, SHA512},
+};
+
+#[cfg(test)]
+fn check_forgery<H: MerkleDamgard>() {
+    let secret = b"YELLOW SUBMARINE";
+    let message = b"comment1=cooking%20MCs;userdata=foo";
+    let suffix = b";admin=true";
+
+    let mac = |message: &[u8]| {
+        let mut hasher = H::new();
+        hasher.update(secret);
+        hasher.update(message);
+        hasher.finalize()
+    };
+
+    let (forged_message, forged_mac) = find_forgery::<H, _>(
+        &mac(message),
+        message,
+        0..64,
+        suffix,
+        |forged_message, forged_mac| mac(forged_message) == forged_mac,
+    )
+    .unwrap();
+
+    assert!(forged_message.starts_with(message));
+    assert!(forged_message.ends_with(suffix));
+    assert_eq!(
+        forged_message.len(),
+        message.len() + H::padding(secret.len() + message.len()).len() + suffix.len()
+    );
+    assert_eq!(forged_mac, mac(&forged_message));
+}
+
+#[test]
+fn test_length_extension_forgeries() {
+    check_forgery::<MD4>();
+    check_forgery::<MD5>();
+    check_forgery::<SHA1>();
+    check_forgery::<SHA256>();
+    check_forgery::<SHA512>();
+}
+
+#[test]
+fn test_length_extension_rejects_partial_state() {
+    let message = b"comment1=cooking%20MCs;userdata=foo";
+
+    assert_eq!(
+        try_extend::<SHA384>(&SHA384::digest(message), message, 16, b";admin=true"),
+        Err(Error::InvalidLength {
+            expected: 64,
+            actual: 48
+        })
+    );
+    assert_eq!(
+        try_extend::<SHA3_256>(&SHA3_256::digest(message), message, 16, b";admin=true"),
+        Err(Error::InvalidLength {
+            expected: 200,
+            actual: 32
+        })
+    );
+    assert_eq!(
+        try_find_forgery::<SHA1, _>(&[0; 4], message, 0..64, b";admin=true", |_, _| true),
+        Err(Error::InvalidLength {
+            expected: 20,
+            actual: 4
+        })
+    );
+}
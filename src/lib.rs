@@ -1,25 +1,46 @@
 pub mod aes;
 pub mod bignum;
+pub mod blake2;
+pub mod block_cipher;
 pub mod bytes;
+pub mod cmac;
+pub mod collision;
 pub mod dh;
 pub mod dh_actor;
 pub mod distance;
 pub mod encoding;
 pub mod english_score;
+pub mod error;
+pub mod gf128;
+pub mod hasher;
+pub mod herding;
 pub mod hmac;
+pub mod kdf;
+pub mod key_wrap;
+pub mod length_extension;
 pub mod md4;
+pub mod md4_collision;
+pub mod md5;
 #[allow(non_snake_case)]
 pub mod mersenne_twister;
+pub mod modes;
+pub mod multicollision;
 pub mod padding;
 pub mod quote;
 pub mod rsa;
+pub mod second_preimage;
 pub mod sha1;
 pub mod sha256;
+pub mod sha3;
+pub mod sha512;
 pub mod srp;
 pub mod string_wrap;
+pub mod toy_hash;
 pub mod utils;
 pub mod xor;
 
+pub use crate::error::Error;
+
 pub mod prelude {
     pub use crate::encoding::{base64_to_bytes, block_pretty_print, bytes_to_hex};
     pub use crate::padding::{pkcs7_pad, pkcs7_unpad};
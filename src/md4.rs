@@ -1,6 +1,7 @@
 use std::mem;
 use std::num::Wrapping;
 
+use crate::hasher::{self, Hasher, MerkleDamgard};
 use crate::padding;
 
 const A: u32 = 0x67452301;
@@ -79,13 +80,7 @@ impl MD4 {
             self.process_chunk(&chunk);
         }
 
-        let mut result = Vec::with_capacity(16);
-        result.extend_from_slice(&self.a.0.to_le_bytes());
-        result.extend_from_slice(&self.b.0.to_le_bytes());
-        result.extend_from_slice(&self.c.0.to_le_bytes());
-        result.extend_from_slice(&self.d.0.to_le_bytes());
-
-        result
+        self.registers()
     }
 
     fn process_chunk(&mut self, chunk: &[u8]) {
@@ -168,8 +163,92 @@ impl MD4 {
 
         self.processed_bits += (chunk.len() * 8) as u64;
     }
+
+    // What the compression of `block` would go through from the current registers: the value
+    // produced by each of the 48 steps, before the registers are added back in. Every step
+    // replaces one register, in the order a, d, c, b
+    pub fn step_values(&self, block: &[u8]) -> Vec<u32> {
+        assert_eq!(block.len(), CHUNK_SIZE_BYTES);
+
+        let x = hasher::le_u32_words(block);
+        let mut registers = [self.a.0, self.b.0, self.c.0, self.d.0];
+        let mut values = Vec::with_capacity(48);
+
+        for step in 0..48 {
+            let round = step / 16;
+            let [a, b, c, d] = registers;
+            let x_k = x[ROUND_WORDS[round][step % 16]];
+            let s = ROUND_SHIFTS[round][step % 4];
+
+            let value = match round {
+                0 => Self::round_1_step(a, b, c, d, x_k, s),
+                1 => Self::round_2_step(a, b, c, d, x_k, s),
+                _ => Self::round_3_step(a, b, c, d, x_k, s),
+            };
+
+            values.push(value);
+            registers = [d, value, b, c];
+        }
+
+        values
+    }
+
+    // Single steps on plain words, for attacks that need to steer the intermediate values
+    pub fn round_1_step(a: u32, b: u32, c: u32, d: u32, x_k: u32, s: u32) -> u32 {
+        let mut a = Wrapping(a);
+        round_1(
+            &mut a,
+            Wrapping(b),
+            Wrapping(c),
+            Wrapping(d),
+            Wrapping(x_k),
+            s,
+        );
+        a.0
+    }
+
+    pub fn round_2_step(a: u32, b: u32, c: u32, d: u32, x_k: u32, s: u32) -> u32 {
+        let mut a = Wrapping(a);
+        round_2(
+            &mut a,
+            Wrapping(b),
+            Wrapping(c),
+            Wrapping(d),
+            Wrapping(x_k),
+            s,
+        );
+        a.0
+    }
+
+    pub fn round_3_step(a: u32, b: u32, c: u32, d: u32, x_k: u32, s: u32) -> u32 {
+        let mut a = Wrapping(a);
+        round_3(
+            &mut a,
+            Wrapping(b),
+            Wrapping(c),
+            Wrapping(d),
+            Wrapping(x_k),
+            s,
+        );
+        a.0
+    }
+
+    // The inverse of `round_1_step`: the message word that makes the step produce `value`
+    pub fn round_1_message_word(value: u32, a: u32, b: u32, c: u32, d: u32, s: u32) -> u32 {
+        let (a, b, c, d) = (Wrapping(a), Wrapping(b), Wrapping(c), Wrapping(d));
+        (Wrapping(value.rotate_right(s)) - a - f(b, c, d)).0
+    }
 }
 
+// Which message word each step of a round takes, and how far it rotates
+const ROUND_WORDS: [[usize; 16]; 3] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15],
+    [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15],
+];
+
+const ROUND_SHIFTS: [[u32; 4]; 3] = [[3, 7, 11, 19], [3, 5, 9, 13], [3, 9, 11, 15]];
+
 fn round_1(
     a: &mut Wrapping<u32>,
     b: Wrapping<u32>,
@@ -222,6 +301,47 @@ fn h(x: Wrapping<u32>, y: Wrapping<u32>, z: Wrapping<u32>) -> Wrapping<u32> {
     x ^ y ^ z
 }
 
+impl Hasher for MD4 {
+    const BLOCK_SIZE: usize = CHUNK_SIZE_BYTES;
+    const OUTPUT_SIZE: usize = 16;
+
+    fn new() -> Self {
+        MD4::new()
+    }
+
+    fn update(&mut self, message: &[u8]) {
+        MD4::update(self, message)
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        MD4::finalize(self)
+    }
+}
+
+impl MerkleDamgard for MD4 {
+    fn from_registers(registers: &[u8], processed_bits: u64) -> Self {
+        let r = hasher::le_u32_words(registers);
+
+        let mut md4 = MD4::with_registers(r[0], r[1], r[2], r[3]);
+        md4.set_processed_bits(processed_bits);
+        md4
+    }
+
+    fn registers(&self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(16);
+        result.extend_from_slice(&self.a.0.to_le_bytes());
+        result.extend_from_slice(&self.b.0.to_le_bytes());
+        result.extend_from_slice(&self.c.0.to_le_bytes());
+        result.extend_from_slice(&self.d.0.to_le_bytes());
+
+        result
+    }
+
+    fn padding(message_len: usize) -> Vec<u8> {
+        padding::md_padding_le_count_for_len(message_len)
+    }
+}
+
 pub fn md4(message: &[u8]) -> Vec<u8> {
     let mut md4 = MD4::new();
     md4.update(&message);
@@ -246,3 +366,20 @@ fn test_md4_quick_brown_fox() {
 
     assert_eq!(hex_result, "1bee69a46ba811185c194762abaeae90");
 }
+
+#[test]
+fn test_md4_step_values() {
+    let block: Vec<u8> = (0..64).collect();
+    let md4 = MD4::new();
+    let values = md4.step_values(&block);
+    assert_eq!(values.len(), 48);
+
+    let registers: Vec<u32> = hasher::le_u32_words(&MD4::compress(&md4.registers(), &block));
+    assert_eq!(registers[0], A.wrapping_add(values[44]));
+    assert_eq!(registers[1], B.wrapping_add(values[47]));
+    assert_eq!(registers[2], C.wrapping_add(values[46]));
+    assert_eq!(registers[3], D.wrapping_add(values[45]));
+
+    let x_0 = hasher::le_u32_words(&block)[0];
+    assert_eq!(MD4::round_1_message_word(values[0], A, B, C, D, 3), x_0);
+}
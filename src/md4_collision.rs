@@ -0,0 +1,350 @@
+use rand::{thread_rng, Rng};
+
+use crate::hasher::{self, MerkleDamgard};
+use crate::md4::MD4;
+
+// Wang et al.'s MD4 collision (challenge 55). Two single-block messages that differ by
+//
+//   m1' = m1 + 2^31, m2' = m2 + 2^31 - 2^28, m12' = m12 - 2^16
+//
+// collide whenever the intermediate values of the first one follow a known differential path,
+// and there's a list of conditions on their bits that's sufficient for that. The first round
+// conditions can all be forced by picking the message words (single-step modification), and the
+// ones on a5 and d5 by going back and adjusting the first round (multi-step modification). The
+// rest of the second round ones, c5 to c6, are checked before hashing anything, and the third
+// round is left to chance: that takes a few hundred thousand candidates
+
+#[derive(Clone, Copy, Debug)]
+enum Condition {
+    Zero(u32),
+    One(u32),
+    // Same bit as the value this many steps before
+    Equal(u32, usize),
+    NotEqual(u32, usize),
+}
+
+use self::Condition::*;
+
+// Indexed by step: a1, d1, c1, b1, a2, ... The paper also has conditions on b9 and a10, which are
+// left to the final check
+const CONDITIONS: [&[Condition]; 23] = [
+    &[Equal(6, 1)],
+    &[Zero(6), Equal(7, 1), Equal(10, 1)],
+    &[One(6), One(7), Zero(10), Equal(25, 1)],
+    &[One(6), Zero(7), Zero(10), Zero(25)],
+    &[One(7), One(10), Zero(25), Equal(13, 1)],
+    &[
+        Zero(13),
+        Equal(18, 1),
+        Equal(19, 1),
+        Equal(20, 1),
+        Equal(21, 1),
+        One(25),
+    ],
+    &[
+        Equal(12, 1),
+        Zero(13),
+        Equal(14, 1),
+        Zero(18),
+        Zero(19),
+        One(20),
+        Zero(21),
+    ],
+    &[
+        One(12),
+        One(13),
+        Zero(14),
+        Equal(16, 1),
+        Zero(18),
+        Zero(19),
+        Zero(20),
+        Zero(21),
+    ],
+    &[
+        One(12),
+        One(13),
+        One(14),
+        Zero(16),
+        Zero(18),
+        Zero(19),
+        Zero(20),
+        One(21),
+        Equal(22, 1),
+        Equal(25, 1),
+    ],
+    &[
+        One(12),
+        One(13),
+        One(14),
+        Zero(16),
+        Zero(19),
+        One(20),
+        One(21),
+        Zero(22),
+        One(25),
+        Equal(29, 1),
+    ],
+    &[
+        One(16),
+        Zero(19),
+        Zero(20),
+        Zero(21),
+        Zero(22),
+        Zero(25),
+        One(29),
+        Equal(31, 1),
+    ],
+    &[
+        Zero(19),
+        One(20),
+        One(21),
+        Equal(22, 1),
+        One(25),
+        Zero(29),
+        Zero(31),
+    ],
+    &[
+        Zero(22),
+        Zero(25),
+        Equal(26, 1),
+        Equal(28, 1),
+        One(29),
+        Zero(31),
+    ],
+    &[Zero(22), Zero(25), One(26), One(28), Zero(29), One(31)],
+    &[Equal(18, 1), One(22), One(25), Zero(26), Zero(28), Zero(29)],
+    &[Zero(18), One(25), One(26), One(28), Zero(29)],
+    &[Equal(18, 2), One(25), Zero(26), Equal(28, 1), Equal(31, 1)],
+    &[
+        Equal(18, 1),
+        Equal(25, 2),
+        Equal(26, 2),
+        Equal(28, 2),
+        Equal(31, 2),
+    ],
+    &[
+        Equal(25, 1),
+        Equal(26, 1),
+        Equal(28, 1),
+        Equal(29, 1),
+        Equal(31, 1),
+    ],
+    &[Equal(28, 1), One(29), Zero(31)],
+    &[One(28), One(31)],
+    &[Equal(28, 2)],
+    &[Equal(28, 1), NotEqual(29, 1), NotEqual(31, 1)],
+];
+
+const ROUND_1_SHIFTS: [u32; 4] = [3, 7, 11, 19];
+const ROUND_2_SHIFTS: [u32; 4] = [3, 5, 9, 13];
+// The message words for the second round steps that have conditions, a5 to c6
+const ROUND_2_WORDS: [usize; 7] = [0, 4, 8, 12, 1, 5, 9];
+
+fn bit(value: u32, index: u32) -> u32 {
+    (value >> index) & 1
+}
+
+// `previous` holds every value so far, starting with the initial registers
+fn satisfy(mut value: u32, conditions: &[Condition], previous: &[u32]) -> u32 {
+    for &condition in conditions {
+        let (index, wanted) = match condition {
+            Zero(index) => (index, 0),
+            One(index) => (index, 1),
+            Equal(index, back) => (index, bit(previous[previous.len() - back], index)),
+            NotEqual(index, back) => (index, bit(previous[previous.len() - back], index) ^ 1),
+        };
+
+        value = (value & !(1 << index)) | (wanted << index);
+    }
+
+    value
+}
+
+fn is_satisfied(value: u32, conditions: &[Condition], previous: &[u32]) -> bool {
+    satisfy(value, conditions, previous) == value
+}
+
+// The arguments to step `step`: the value it replaces, and the three that came after that one.
+// `values` starts with the initial registers, in the order a, d, c, b
+fn step_inputs(values: &[u32], step: usize) -> (u32, u32, u32, u32) {
+    (
+        values[step],
+        values[step + 3],
+        values[step + 2],
+        values[step + 1],
+    )
+}
+
+// Forces all the first round conditions, by computing each step, fixing up its bits, and then
+// working out the message word that produces the fixed value
+fn single_step_modification(initial: &[u32], words: &mut [u32]) -> Vec<u32> {
+    let mut values = initial.to_vec();
+
+    for step in 0..16 {
+        let (a, b, c, d) = step_inputs(&values, step);
+        let s = ROUND_1_SHIFTS[step % 4];
+
+        let value = MD4::round_1_step(a, b, c, d, words[step], s);
+        let value = satisfy(value, CONDITIONS[step], &values);
+
+        words[step] = MD4::round_1_message_word(value, a, b, c, d, s);
+        values.push(value);
+    }
+
+    values
+}
+
+fn round_2_step(values: &[u32], words: &[u32], step: usize) -> u32 {
+    let (a, b, c, d) = step_inputs(values, step);
+    let x_k = words[ROUND_2_WORDS[step - 16]];
+
+    MD4::round_2_step(a, b, c, d, x_k, ROUND_2_SHIFTS[step % 4])
+}
+
+// a5 takes m0, which also produced a1. Flipping bit i of a1 changes m0 by about 2^(i - 3), which
+// in turn flips bit i of a5. The next four message words then absorb the change to a1, so the
+// rest of the first round comes out the same. d5 works the same way through m4 and a2, except
+// that it rotates by 5, so bit i of d5 comes from bit i - 2 of a2. Appends a5 and d5 to `values`
+fn multi_step_modification(values: &mut Vec<u32>, words: &mut [u32]) {
+    for step in 16..18 {
+        let round_1_step = ROUND_2_WORDS[step - 16];
+        let shift = ROUND_2_SHIFTS[step % 4];
+
+        for &condition in CONDITIONS[step] {
+            if is_satisfied(round_2_step(values, words, step), &[condition], values) {
+                continue;
+            }
+
+            let index = match condition {
+                Zero(index) | One(index) | Equal(index, _) | NotEqual(index, _) => index,
+            };
+            values[4 + round_1_step] ^= 1 << (index + 3 - shift);
+
+            for later_step in round_1_step..round_1_step + 5 {
+                let (a, b, c, d) = step_inputs(values, later_step);
+                let s = ROUND_1_SHIFTS[later_step % 4];
+                words[later_step] =
+                    MD4::round_1_message_word(values[4 + later_step], a, b, c, d, s);
+            }
+        }
+
+        let value = round_2_step(values, words, step);
+        values.push(value);
+    }
+}
+
+// Whether the rest of the second round conditions hold as well, from c5 on. Computing those steps
+// is much cheaper than hashing both messages, and most candidates miss one of them
+fn satisfies_round_2(values: &mut Vec<u32>, words: &[u32]) -> bool {
+    for (step, &conditions) in CONDITIONS.iter().enumerate().skip(18) {
+        let value = round_2_step(values, words, step);
+        if !is_satisfied(value, conditions, values) {
+            return false;
+        }
+
+        values.push(value);
+    }
+
+    true
+}
+
+fn to_block(words: &[u32]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+// The other half of a candidate pair
+pub fn apply_differential(block: &[u8]) -> Vec<u8> {
+    let mut words = hasher::le_u32_words(block);
+    words[1] = words[1].wrapping_add(1 << 31);
+    words[2] = words[2].wrapping_add(1 << 31).wrapping_sub(1 << 28);
+    words[12] = words[12].wrapping_sub(1 << 16);
+
+    to_block(&words)
+}
+
+// Two different 64-byte messages with the same MD4 hash, and how many candidates it took
+pub fn find_collision() -> (Vec<u8>, Vec<u8>, u64) {
+    let registers = MD4::new().registers();
+    let r = hasher::le_u32_words(&registers);
+    let initial = [r[0], r[3], r[2], r[1]];
+
+    let mut rng = thread_rng();
+    let mut words = [0_u32; 16];
+    let mut attempts = 0;
+
+    loop {
+        attempts += 1;
+        rng.fill(&mut words[..]);
+
+        let mut values = single_step_modification(&initial, &mut words);
+        multi_step_modification(&mut values, &mut words);
+
+        if !satisfies_round_2(&mut values, &words) {
+            continue;
+        }
+
+        let block = to_block(&words);
+        let other = apply_differential(&block);
+
+        if MD4::compress(&registers, &block) == MD4::compress(&registers, &other) {
+            return (block, other, attempts);
+        }
+    }
+}
+
+#[cfg(test)]
+use crate::{encoding::hex_to_bytes, md4::md4};
+
+#[test]
+fn test_known_collision() {
+    let block = hex_to_bytes(
+        "9728d95179178f5bf928f311159ad7a87c94fa8a0da3da7953e1170a00e17058\
+         ca8fb8246723ee080f07ca86e5957ad3f0b65a6bd63fcd98ce6127ed101ec4e6",
+    );
+    let other = apply_differential(&block);
+
+    assert_eq!(
+        other,
+        hex_to_bytes(
+            "9728d95179178fdbf928f381159ad7a87c94fa8a0da3da7953e1170a00e17058\
+             ca8fb8246723ee080f07ca86e5957ad3f0b6596bd63fcd98ce6127ed101ec4e6"
+        )
+    );
+    assert_eq!(md4(&block), md4(&other));
+}
+
+#[test]
+fn test_modifications_satisfy_conditions() {
+    let registers = MD4::new().registers();
+    let r = hasher::le_u32_words(&registers);
+    let initial = [r[0], r[3], r[2], r[1]];
+
+    let mut rng = thread_rng();
+
+    for _ in 0..100 {
+        let mut words = [0_u32; 16];
+        rng.fill(&mut words[..]);
+
+        let mut values = single_step_modification(&initial, &mut words);
+        multi_step_modification(&mut values, &mut words);
+
+        let mut all_values = initial.to_vec();
+        all_values.extend(MD4::new().step_values(&to_block(&words)));
+
+        for step in 0..18 {
+            assert!(is_satisfied(
+                all_values[4 + step],
+                CONDITIONS[step],
+                &all_values[..4 + step]
+            ));
+        }
+    }
+}
+
+#[test]
+fn test_find_collision() {
+    let (block, other, _) = find_collision();
+
+    assert_ne!(block, other);
+    assert_eq!(md4(&block), md4(&other));
+}
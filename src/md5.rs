@@ -0,0 +1,288 @@
+use std::mem;
+use std::num::Wrapping;
+
+use crate::hasher::{self, Hasher, MerkleDamgard};
+use crate::padding;
+
+const A: u32 = 0x67452301;
+const B: u32 = 0xefcdab89;
+const C: u32 = 0x98badcfe;
+const D: u32 = 0x10325476;
+
+// floor(abs(sin(i + 1)) * 2^32)
+const T: &[u32] = &[
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+// Left rotations for each step, which repeat every four steps within a round
+const S: [[u32; 4]; 4] = [
+    [7, 12, 17, 22],
+    [5, 9, 14, 20],
+    [4, 11, 16, 23],
+    [6, 10, 15, 21],
+];
+
+pub struct MD5 {
+    a: Wrapping<u32>,
+    b: Wrapping<u32>,
+    c: Wrapping<u32>,
+    d: Wrapping<u32>,
+    processed_bits: u64,
+    incomplete_chunk: Vec<u8>,
+    x_buffer: Vec<Wrapping<u32>>,
+}
+
+const CHUNK_SIZE_BYTES: usize = 64;
+
+impl MD5 {
+    pub fn new() -> Self {
+        Self::with_registers(A, B, C, D)
+    }
+
+    pub fn with_registers(a: u32, b: u32, c: u32, d: u32) -> Self {
+        Self {
+            a: Wrapping(a),
+            b: Wrapping(b),
+            c: Wrapping(c),
+            d: Wrapping(d),
+            x_buffer: Vec::with_capacity(16),
+            incomplete_chunk: Vec::with_capacity(CHUNK_SIZE_BYTES),
+            processed_bits: 0,
+        }
+    }
+
+    pub fn set_processed_bits(&mut self, processed_bits: u64) {
+        self.processed_bits = processed_bits;
+    }
+
+    pub fn update(&mut self, mut message: &[u8]) {
+        if self.incomplete_chunk.len() + message.len() < CHUNK_SIZE_BYTES {
+            self.incomplete_chunk.extend_from_slice(message);
+            return;
+        }
+
+        if !self.incomplete_chunk.is_empty() {
+            let mut incomplete = mem::replace(
+                &mut self.incomplete_chunk,
+                Vec::with_capacity(CHUNK_SIZE_BYTES),
+            );
+
+            let take_from_message = CHUNK_SIZE_BYTES - incomplete.len();
+            let (left, right) = message.split_at(take_from_message);
+
+            incomplete.extend_from_slice(left);
+
+            self.process_chunk(&incomplete);
+            message = right;
+        }
+
+        for chunk in message.chunks(CHUNK_SIZE_BYTES) {
+            if chunk.len() == CHUNK_SIZE_BYTES {
+                self.process_chunk(chunk);
+            } else {
+                self.incomplete_chunk.extend_from_slice(chunk);
+                return;
+            }
+        }
+    }
+
+    pub fn finalize(mut self) -> Vec<u8> {
+        let final_chunks =
+            padding::md_padding_le_count(&self.incomplete_chunk, self.processed_bits);
+        for chunk in final_chunks.chunks(CHUNK_SIZE_BYTES) {
+            self.process_chunk(chunk);
+        }
+
+        self.registers()
+    }
+
+    fn process_chunk(&mut self, chunk: &[u8]) {
+        assert_eq!(chunk.len(), CHUNK_SIZE_BYTES);
+
+        let x = &mut self.x_buffer;
+
+        x.clear();
+        x.extend(hasher::le_u32_words(chunk).into_iter().map(Wrapping));
+
+        let (mut a, mut b, mut c, mut d) = (self.a, self.b, self.c, self.d);
+
+        for step in 0..64 {
+            let round = step / 16;
+
+            // Each round walks through the message words in a different order
+            let (mixed, k) = match round {
+                0 => (f(b, c, d), step),
+                1 => (g(b, c, d), (5 * step + 1) % 16),
+                2 => (h(b, c, d), (3 * step + 5) % 16),
+                _ => (i(b, c, d), (7 * step) % 16),
+            };
+
+            let rotated = (a + mixed + x[k] + Wrapping(T[step]))
+                .0
+                .rotate_left(S[round][step % 4]);
+
+            a = d;
+            d = c;
+            c = b;
+            b += Wrapping(rotated);
+        }
+
+        self.a += a;
+        self.b += b;
+        self.c += c;
+        self.d += d;
+
+        self.processed_bits += (chunk.len() * 8) as u64;
+    }
+}
+
+impl Default for MD5 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn f(x: Wrapping<u32>, y: Wrapping<u32>, z: Wrapping<u32>) -> Wrapping<u32> {
+    (x & y) | (!x & z)
+}
+
+fn g(x: Wrapping<u32>, y: Wrapping<u32>, z: Wrapping<u32>) -> Wrapping<u32> {
+    (x & z) | (y & !z)
+}
+
+fn h(x: Wrapping<u32>, y: Wrapping<u32>, z: Wrapping<u32>) -> Wrapping<u32> {
+    x ^ y ^ z
+}
+
+fn i(x: Wrapping<u32>, y: Wrapping<u32>, z: Wrapping<u32>) -> Wrapping<u32> {
+    y ^ (x | !z)
+}
+
+impl Hasher for MD5 {
+    const BLOCK_SIZE: usize = CHUNK_SIZE_BYTES;
+    const OUTPUT_SIZE: usize = 16;
+
+    fn new() -> Self {
+        MD5::new()
+    }
+
+    fn update(&mut self, message: &[u8]) {
+        MD5::update(self, message)
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        MD5::finalize(self)
+    }
+}
+
+impl MerkleDamgard for MD5 {
+    fn from_registers(registers: &[u8], processed_bits: u64) -> Self {
+        let r = hasher::le_u32_words(registers);
+
+        let mut md5 = MD5::with_registers(r[0], r[1], r[2], r[3]);
+        md5.set_processed_bits(processed_bits);
+        md5
+    }
+
+    fn registers(&self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(16);
+        result.extend_from_slice(&self.a.0.to_le_bytes());
+        result.extend_from_slice(&self.b.0.to_le_bytes());
+        result.extend_from_slice(&self.c.0.to_le_bytes());
+        result.extend_from_slice(&self.d.0.to_le_bytes());
+
+        result
+    }
+
+    fn padding(message_len: usize) -> Vec<u8> {
+        padding::md_padding_le_count_for_len(message_len)
+    }
+}
+
+pub fn md5(message: &[u8]) -> Vec<u8> {
+    let mut md5 = MD5::new();
+    md5.update(message);
+    md5.finalize()
+}
+
+#[cfg(test)]
+use crate::encoding::bytes_to_hex;
+
+#[test]
+fn test_md5_rfc1321_vectors() {
+    let vectors: [(&[u8], &str); 7] = [
+        (b"", "d41d8cd98f00b204e9800998ecf8427e"),
+        (b"a", "0cc175b9c0f1b6a831c399e269772661"),
+        (b"abc", "900150983cd24fb0d6963f7d28e17f72"),
+        (b"message digest", "f96b697d7cb7938d525a2f31aaf161d0"),
+        (
+            b"abcdefghijklmnopqrstuvwxyz",
+            "c3fcd3d76192e4007dfb496cca67e13b",
+        ),
+        (
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+            "d174ab98d277d9f5a5611c2c9f419d9f",
+        ),
+        (
+            b"12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+            "57edf4a22be3c955ac49da2e2107b67a",
+        ),
+    ];
+
+    for &(message, expected) in vectors.iter() {
+        assert_eq!(bytes_to_hex(&md5(message)), expected);
+    }
+}
+
+#[test]
+fn test_md5_incremental() {
+    let mut md5 = MD5::new();
+    md5.update(b"The quick brown fox");
+    md5.update(b" jumps over the lazy dog");
+
+    assert_eq!(
+        bytes_to_hex(&md5.finalize()),
+        "9e107d9d372bb6826bd81d3542a419d6"
+    );
+}
+
+// The challenge 30 forgery: a secret-prefix MAC can be extended without knowing the secret, by
+// picking the hash up from the MAC and pretending the glue padding was part of the message
+#[test]
+fn test_md5_secret_prefix_mac_length_extension() {
+    use std::convert::TryInto;
+
+    let secret = b"YELLOW SUBMARINE";
+    let message = b"comment1=cooking%20MCs;userdata=foo";
+    let suffix = b";admin=true";
+
+    let mut keyed = secret.to_vec();
+    keyed.extend_from_slice(message);
+    let mac = md5(&keyed);
+
+    let glue_padding = padding::md_padding_le_count_for_len(secret.len() + message.len());
+    let mut forged_message = message.to_vec();
+    forged_message.extend(glue_padding);
+    let forged_len = secret.len() + forged_message.len();
+    forged_message.extend_from_slice(suffix);
+
+    let r: Vec<u32> = mac
+        .chunks(4)
+        .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+        .collect();
+    let mut md5 = MD5::with_registers(r[0], r[1], r[2], r[3]);
+    md5.set_processed_bits((forged_len * 8) as u64);
+    md5.update(suffix);
+    let forged_mac = md5.finalize();
+
+    let mut keyed = secret.to_vec();
+    keyed.extend_from_slice(&forged_message);
+    assert_eq!(forged_mac, self::md5(&keyed));
+}
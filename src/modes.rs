@@ -0,0 +1,48 @@
+use crate::error::{or_panic, Error, Result};
+use crate::xor::xor_in_place;
+
+mod cbc;
+mod cfb;
+mod ctr;
+mod cts;
+mod ecb;
+mod gcm;
+mod ofb;
+mod pcbc;
+mod siv;
+mod stream;
+mod xts;
+
+pub use self::cbc::Cbc;
+pub use self::cfb::Cfb;
+pub use self::ctr::{Ctr, CtrParams, Endianness, Keystream};
+pub use self::cts::{CbcCts, CtsVariant};
+pub use self::ecb::Ecb;
+pub use self::gcm::{ghash, Gcm};
+pub use self::ofb::Ofb;
+pub use self::pcbc::Pcbc;
+pub use self::siv::Siv;
+pub use self::stream::{CbcDecryptor, CbcEncryptor, CtrStream};
+pub use self::xts::Xts;
+
+fn check_block_alignment(bytes: &[u8], block_size: usize) -> Result<()> {
+    if !bytes.len().is_multiple_of(block_size) {
+        return Err(Error::NotBlockAligned {
+            block_size,
+            length: bytes.len(),
+        });
+    }
+
+    Ok(())
+}
+
+fn check_iv_len(iv: &[u8], block_size: usize) -> Result<()> {
+    if iv.len() != block_size {
+        return Err(Error::InvalidLength {
+            expected: block_size,
+            actual: iv.len(),
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use crate::block_cipher::BlockCipher;
+use crate::error::Result;
+use crate::padding::{Padding, Pkcs7};
+
+use super::{or_panic, xor_in_place};
+
+pub struct Cbc<C, P = Pkcs7> {
+    cipher: C,
+    padding: P,
+}
+
+impl<C: BlockCipher> Cbc<C> {
+    pub fn new(cipher: C) -> Self {
+        Self::with_padding(cipher, Pkcs7)
+    }
+}
+
+impl<C: BlockCipher, P: Padding> Cbc<C, P> {
+    pub fn with_padding(cipher: C, padding: P) -> Self {
+        Self { cipher, padding }
+    }
+
+    pub fn cipher(&self) -> &C {
+        &self.cipher
+    }
+
+    pub fn padding(&self) -> &P {
+        &self.padding
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8], iv: &[u8]) -> Vec<u8> {
+        or_panic(self.try_encrypt(plaintext, iv))
+    }
+
+    pub fn try_encrypt(&self, plaintext: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+        self.try_encrypt_blocks(&self.padding.pad(plaintext, C::BLOCK_SIZE), iv)
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+        self.padding
+            .unpad(&self.try_decrypt_blocks(ciphertext, iv)?, C::BLOCK_SIZE)
+    }
+
+    pub fn encrypt_blocks(&self, plaintext: &[u8], iv: &[u8]) -> Vec<u8> {
+        or_panic(self.try_encrypt_blocks(plaintext, iv))
+    }
+
+    pub fn try_encrypt_blocks(&self, plaintext: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+        let mut ciphertext = plaintext.to_vec();
+        self.try_encrypt_in_place(&mut ciphertext, iv)?;
+        Ok(ciphertext)
+    }
+
+    pub fn decrypt_blocks(&self, ciphertext: &[u8], iv: &[u8]) -> Vec<u8> {
+        or_panic(self.try_decrypt_blocks(ciphertext, iv))
+    }
+
+    pub fn try_decrypt_blocks(&self, ciphertext: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+        let mut plaintext = ciphertext.to_vec();
+        self.try_decrypt_in_place(&mut plaintext, iv)?;
+        Ok(plaintext)
+    }
+
+    pub fn encrypt_in_place(&self, blocks: &mut [u8], iv: &[u8]) {
+        or_panic(self.try_encrypt_in_place(blocks, iv))
+    }
+
+    pub fn decrypt_in_place(&self, blocks: &mut [u8], iv: &[u8]) {
+        or_panic(self.try_decrypt_in_place(blocks, iv))
+    }
+
+    pub fn try_encrypt_in_place(&self, blocks: &mut [u8], iv: &[u8]) -> Result<()> {
+        super::check_iv_len(iv, C::BLOCK_SIZE)?;
+        super::check_block_alignment(blocks, C::BLOCK_SIZE)?;
+
+        let mut previous_block = iv;
+
+        for block in blocks.chunks_mut(C::BLOCK_SIZE) {
+            xor_in_place(block, previous_block);
+            self.cipher.encrypt_block(block);
+
+            previous_block = block;
+        }
+
+        Ok(())
+    }
+
+    pub fn try_decrypt_in_place(&self, blocks: &mut [u8], iv: &[u8]) -> Result<()> {
+        super::check_iv_len(iv, C::BLOCK_SIZE)?;
+        super::check_block_alignment(blocks, C::BLOCK_SIZE)?;
+
+        let mut previous_block = iv.to_vec();
+        let mut current_block = vec![0; C::BLOCK_SIZE];
+
+        for block in blocks.chunks_mut(C::BLOCK_SIZE) {
+            current_block.copy_from_slice(block);
+
+            self.cipher.decrypt_block(block);
+            xor_in_place(block, &previous_block);
+
+            std::mem::swap(&mut previous_block, &mut current_block);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use crate::block_cipher::BlockCipher;
+use crate::error::Result;
+
+use super::{or_panic, xor_in_place};
+
+// Cipher feedback mode with `segment_bits`-bit segments. Like CTR it's a stream mode (no
+// padding), but the keystream depends on the previous ciphertext, so a flipped ciphertext bit
+// flips the same plaintext bit and garbles the following block's worth of segments
+pub struct Cfb<C> {
+    cipher: C,
+    segment_bits: usize,
+}
+
+impl<C: BlockCipher> Cfb<C> {
+    // CFB-1, CFB-8 and CFB-128 from SP 800-38A; any whole number of bytes up to the block size
+    // works too
+    pub fn new(cipher: C, segment_bits: usize) -> Self {
+        let valid_segment = segment_bits == 1
+            || (segment_bits.is_multiple_of(8) && (8..=8 * C::BLOCK_SIZE).contains(&segment_bits));
+
+        if !valid_segment {
+            panic!("Invalid CFB segment size: {} bits", segment_bits);
+        }
+
+        Self {
+            cipher,
+            segment_bits,
+        }
+    }
+
+    pub fn full_block(cipher: C) -> Self {
+        Self::new(cipher, 8 * C::BLOCK_SIZE)
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8], iv: &[u8]) -> Vec<u8> {
+        or_panic(self.try_encrypt(plaintext, iv))
+    }
+
+    pub fn try_encrypt(&self, plaintext: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+        let mut ciphertext = plaintext.to_vec();
+        self.process(&mut ciphertext, iv, false)?;
+        Ok(ciphertext)
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8], iv: &[u8]) -> Vec<u8> {
+        or_panic(self.try_decrypt(ciphertext, iv))
+    }
+
+    pub fn try_decrypt(&self, ciphertext: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+        let mut plaintext = ciphertext.to_vec();
+        self.process(&mut plaintext, iv, true)?;
+        Ok(plaintext)
+    }
+
+    // The shift register is always fed with ciphertext, which is the input when decrypting and
+    // the output when encrypting
+    fn process(&self, data: &mut [u8], iv: &[u8], decrypting: bool) -> Result<()> {
+        super::check_iv_len(iv, C::BLOCK_SIZE)?;
+
+        let mut register = iv.to_vec();
+        let mut output = vec![0; C::BLOCK_SIZE];
+
+        if self.segment_bits == 1 {
+            for byte in data.iter_mut() {
+                let mut result = 0;
+
+                for bit in (0..8).rev() {
+                    output.copy_from_slice(&register);
+                    self.cipher.encrypt_block(&mut output);
+
+                    let input_bit = (*byte >> bit) & 1;
+                    let output_bit = input_bit ^ (output[0] >> 7);
+                    result |= output_bit << bit;
+
+                    let ciphertext_bit = if decrypting { input_bit } else { output_bit };
+                    shift_in_bit(&mut register, ciphertext_bit);
+                }
+
+                *byte = result;
+            }
+
+            return Ok(());
+        }
+
+        let segment_len = self.segment_bits / 8;
+
+        for segment in data.chunks_mut(segment_len) {
+            output.copy_from_slice(&register);
+            self.cipher.encrypt_block(&mut output);
+
+            register.copy_within(segment_len.., 0);
+            let register_tail = C::BLOCK_SIZE - segment_len;
+
+            if decrypting {
+                register[register_tail..][..segment.len()].copy_from_slice(segment);
+                xor_in_place(segment, &output);
+            } else {
+                xor_in_place(segment, &output);
+                register[register_tail..][..segment.len()].copy_from_slice(segment);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn shift_in_bit(register: &mut [u8], bit: u8) {
+    let mut carry = bit;
+
+    for byte in register.iter_mut().rev() {
+        let next_carry = *byte >> 7;
+        *byte = (*byte << 1) | carry;
+        carry = next_carry;
+    }
+}
+
+#[cfg(test)]
+use crate::{
+    aes::Aes128,
+    encoding::{bytes_to_hex, hex_to_bytes},
+};
+
+#[cfg(test)]
+fn check_vector(segment_bits: usize, plaintext: &str, expected_ciphertext: &str) {
+    let key = hex_to_bytes("2b7e151628aed2a6abf7158809cf4f3c");
+    let iv = hex_to_bytes("000102030405060708090a0b0c0d0e0f");
+    let cfb = Cfb::new(Aes128::new(&key), segment_bits);
+
+    let plaintext = hex_to_bytes(plaintext);
+    let ciphertext = cfb.encrypt(&plaintext, &iv);
+    assert_eq!(bytes_to_hex(&ciphertext), expected_ciphertext);
+    assert_eq!(cfb.decrypt(&ciphertext, &iv), plaintext);
+}
+
+#[test]
+fn test_cfb_nist_vectors() {
+    // SP 800-38A F.3.1 (CFB1-AES128), F.3.7 (CFB8-AES128) and F.3.13 (CFB128-AES128)
+    check_vector(1, "6bc1", "68b3");
+    check_vector(
+        8,
+        "6bc1bee22e409f96e93d7e117393172aae2d",
+        "3b79424c9c0dd436bace9e0ed4586a4f32b9",
+    );
+    check_vector(
+        128,
+        "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+         30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+        "3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b\
+         26751f67a3cbb140b1808cf187a4f4dfc04b05357c5d1c0eeac4c66f9ff7f2e6",
+    );
+}
+
+#[test]
+fn test_cfb_error_propagation() {
+    let cfb = Cfb::full_block(Aes128::new(b"YELLOW SUBMARINE"));
+    let iv = [0; 16];
+    let plaintext = [0; 64];
+
+    let mut ciphertext = cfb.encrypt(&plaintext, &iv);
+    ciphertext[5] ^= 1;
+    let decrypted = cfb.decrypt(&ciphertext, &iv);
+
+    // The flipped bit shows up in place, the next block is garbled, and then it recovers
+    assert_eq!(decrypted[5], 1);
+    assert_ne!(&decrypted[16..32], &plaintext[16..32]);
+    assert_eq!(&decrypted[32..], &plaintext[32..]);
+}
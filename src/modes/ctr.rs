@@ -0,0 +1,367 @@
+use crate::block_cipher::BlockCipher;
+use crate::error::{Error, Result};
+
+use super::{or_panic, xor_in_place};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+// The counter block is the nonce followed by a `counter_len`-byte counter, which wraps around
+// without ever carrying into the nonce
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CtrParams {
+    pub nonce_len: usize,
+    pub counter_len: usize,
+    pub endianness: Endianness,
+}
+
+impl CtrParams {
+    // 8-byte nonce followed by a 64-bit little-endian counter, as described on challenge 18
+    pub const CRYPTOPALS: CtrParams = CtrParams::new(8, 8, Endianness::Little);
+
+    // 96-bit nonce followed by a 32-bit big-endian counter, as used by GCM
+    pub const GCM: CtrParams = CtrParams::new(12, 4, Endianness::Big);
+
+    // The whole IV is a 128-bit big-endian counter, as used by SP 800-38A
+    pub const FULL_BLOCK: CtrParams = CtrParams::new(0, 16, Endianness::Big);
+
+    pub const fn new(nonce_len: usize, counter_len: usize, endianness: Endianness) -> Self {
+        Self {
+            nonce_len,
+            counter_len,
+            endianness,
+        }
+    }
+
+    fn counter_mask(&self) -> u128 {
+        if self.counter_len >= 16 {
+            u128::MAX
+        } else {
+            (1 << (8 * self.counter_len)) - 1
+        }
+    }
+
+    fn read_counter(&self, bytes: &[u8]) -> u128 {
+        let mut buffer = [0; 16];
+
+        match self.endianness {
+            Endianness::Little => {
+                buffer[..bytes.len()].copy_from_slice(bytes);
+                u128::from_le_bytes(buffer)
+            }
+
+            Endianness::Big => {
+                buffer[16 - bytes.len()..].copy_from_slice(bytes);
+                u128::from_be_bytes(buffer)
+            }
+        }
+    }
+
+    fn write_counter(&self, counter: u128, target: &mut [u8]) {
+        match self.endianness {
+            Endianness::Little => target.copy_from_slice(&counter.to_le_bytes()[..target.len()]),
+            Endianness::Big => target.copy_from_slice(&counter.to_be_bytes()[16 - target.len()..]),
+        }
+    }
+}
+
+pub struct Ctr<C> {
+    cipher: C,
+    params: CtrParams,
+}
+
+impl<C: BlockCipher> Ctr<C> {
+    // Defaults to splitting the block in half between the nonce and a little-endian counter,
+    // which is `CtrParams::CRYPTOPALS` for 128-bit blocks
+    pub fn new(cipher: C) -> Self {
+        let nonce_len = C::BLOCK_SIZE / 2;
+        let params = CtrParams::new(nonce_len, C::BLOCK_SIZE - nonce_len, Endianness::Little);
+
+        Self::with_params(cipher, params)
+    }
+
+    pub fn with_params(cipher: C, params: CtrParams) -> Self {
+        if params.nonce_len + params.counter_len != C::BLOCK_SIZE {
+            panic!(
+                "Nonce and counter must fill the {}-byte block (got {} + {})",
+                C::BLOCK_SIZE,
+                params.nonce_len,
+                params.counter_len
+            );
+        }
+
+        if params.counter_len == 0 || params.counter_len > 16 {
+            panic!("Counter must have between 1 and 16 bytes");
+        }
+
+        Self { cipher, params }
+    }
+
+    pub fn cipher(&self) -> &C {
+        &self.cipher
+    }
+
+    pub fn params(&self) -> CtrParams {
+        self.params
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8], nonce: &[u8]) -> Vec<u8> {
+        or_panic(self.try_encrypt(plaintext, nonce))
+    }
+
+    pub fn try_encrypt(&self, plaintext: &[u8], nonce: &[u8]) -> Result<Vec<u8>> {
+        let mut ciphertext = plaintext.to_vec();
+        self.try_apply_keystream(&mut ciphertext, nonce)?;
+        Ok(ciphertext)
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8], nonce: &[u8]) -> Vec<u8> {
+        or_panic(self.try_decrypt(ciphertext, nonce))
+    }
+
+    pub fn try_decrypt(&self, ciphertext: &[u8], nonce: &[u8]) -> Result<Vec<u8>> {
+        let mut plaintext = ciphertext.to_vec();
+        self.try_apply_keystream(&mut plaintext, nonce)?;
+        Ok(plaintext)
+    }
+
+    pub fn apply_keystream(&self, data: &mut [u8], nonce: &[u8]) {
+        or_panic(self.try_apply_keystream(data, nonce))
+    }
+
+    pub fn try_apply_keystream(&self, data: &mut [u8], nonce: &[u8]) -> Result<()> {
+        self.try_keystream(nonce)?.apply(data);
+        Ok(())
+    }
+
+    pub fn edit(
+        &self,
+        original_ciphertext: &[u8],
+        nonce: &[u8],
+        offset: usize,
+        new_plaintext: &[u8],
+    ) -> Vec<u8> {
+        or_panic(self.try_edit(original_ciphertext, nonce, offset, new_plaintext))
+    }
+
+    // Editing can't grow the ciphertext, so the new plaintext must fit after `offset`
+    pub fn try_edit(
+        &self,
+        original_ciphertext: &[u8],
+        nonce: &[u8],
+        offset: usize,
+        new_plaintext: &[u8],
+    ) -> Result<Vec<u8>> {
+        let available = original_ciphertext.len().saturating_sub(offset);
+        if new_plaintext.len() > available {
+            return Err(Error::InvalidLength {
+                expected: available,
+                actual: new_plaintext.len(),
+            });
+        }
+
+        let mut keystream = self.try_keystream(nonce)?;
+
+        let mut result = original_ciphertext.to_vec();
+        let edited = &mut result[offset..offset + new_plaintext.len()];
+        edited.copy_from_slice(new_plaintext);
+
+        keystream.seek(offset as u64);
+        keystream.apply(edited);
+
+        Ok(result)
+    }
+
+    pub fn keystream(&self, iv: &[u8]) -> Keystream<'_, C> {
+        or_panic(self.try_keystream(iv))
+    }
+
+    // `iv` is either just the nonce (and the counter starts at zero) or a whole counter block,
+    // in which case the counter starts from the value on its counter bytes
+    pub fn try_keystream(&self, iv: &[u8]) -> Result<Keystream<'_, C>> {
+        let params = self.params;
+        let mut initial_block = vec![0; C::BLOCK_SIZE];
+
+        let initial_counter = if iv.len() == params.nonce_len {
+            initial_block[..params.nonce_len].copy_from_slice(iv);
+            0
+        } else if iv.len() == C::BLOCK_SIZE {
+            initial_block.copy_from_slice(iv);
+            params.read_counter(&iv[params.nonce_len..])
+        } else {
+            return Err(Error::InvalidLength {
+                expected: params.nonce_len,
+                actual: iv.len(),
+            });
+        };
+
+        Ok(Keystream {
+            ctr: self,
+            initial_block,
+            initial_counter,
+            position: 0,
+            block: vec![0; C::BLOCK_SIZE],
+            block_index: None,
+        })
+    }
+}
+
+pub struct Keystream<'a, C> {
+    ctr: &'a Ctr<C>,
+    initial_block: Vec<u8>,
+    initial_counter: u128,
+    position: u64,
+    block: Vec<u8>,
+    block_index: Option<u64>,
+}
+
+impl<'a, C: BlockCipher> Keystream<'a, C> {
+    // The keystream block is only computed once a byte from it is needed, so seeking costs at
+    // most a single block encryption regardless of the offset
+    pub fn seek(&mut self, offset: u64) {
+        self.position = offset;
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn apply(&mut self, mut data: &mut [u8]) {
+        let block_size = C::BLOCK_SIZE as u64;
+
+        while !data.is_empty() {
+            let block_offset = (self.position % block_size) as usize;
+            self.load_block(self.position / block_size);
+
+            let count = data.len().min(C::BLOCK_SIZE - block_offset);
+            let (current, rest) = data.split_at_mut(count);
+
+            xor_in_place(current, &self.block[block_offset..]);
+
+            self.position += count as u64;
+            data = rest;
+        }
+    }
+
+    fn load_block(&mut self, index: u64) {
+        if self.block_index == Some(index) {
+            return;
+        }
+
+        let params = self.ctr.params;
+        let counter = self.initial_counter.wrapping_add(index as u128) & params.counter_mask();
+
+        self.block.copy_from_slice(&self.initial_block);
+        params.write_counter(counter, &mut self.block[params.nonce_len..]);
+        self.ctr.cipher.encrypt_block(&mut self.block);
+
+        self.block_index = Some(index);
+    }
+}
+
+impl<'a, C: BlockCipher> Iterator for Keystream<'a, C> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        let block_size = C::BLOCK_SIZE as u64;
+        self.load_block(self.position / block_size);
+
+        let byte = self.block[(self.position % block_size) as usize];
+        self.position += 1;
+
+        Some(byte)
+    }
+}
+
+#[cfg(test)]
+use crate::aes::Aes128;
+
+#[test]
+fn test_apply_keystream_matches_keystream_iterator() {
+    let ctr = Ctr::new(Aes128::new(b"YELLOW SUBMARINE"));
+    let nonce = [7; 8];
+
+    let mut data = vec![0; 100];
+    ctr.apply_keystream(&mut data, &nonce);
+
+    let expected: Vec<u8> = ctr.keystream(&nonce).take(100).collect();
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn test_full_block_counter_nist_vector() {
+    use crate::encoding::{bytes_to_hex, hex_to_bytes};
+
+    let key = hex_to_bytes("2b7e151628aed2a6abf7158809cf4f3c");
+    let ctr = Ctr::with_params(Aes128::new(&key), CtrParams::FULL_BLOCK);
+
+    let initial_counter = hex_to_bytes("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+    let plaintext =
+        hex_to_bytes("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51");
+
+    assert_eq!(
+        bytes_to_hex(&ctr.encrypt(&plaintext, &initial_counter)),
+        "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff"
+    );
+}
+
+#[test]
+fn test_counter_wraps_within_its_width() {
+    let ctr = Ctr::with_params(Aes128::new(b"YELLOW SUBMARINE"), CtrParams::GCM);
+
+    let mut last_block = [0xaa; 16];
+    last_block[12..].copy_from_slice(&[0xff; 4]);
+
+    let mut first_block = [0xaa; 16];
+    first_block[12..].copy_from_slice(&[0; 4]);
+
+    let wrapped: Vec<u8> = ctr.keystream(&last_block).skip(16).take(16).collect();
+    let expected: Vec<u8> = ctr.keystream(&first_block).take(16).collect();
+
+    assert_eq!(wrapped, expected);
+}
+
+#[test]
+fn test_seek_costs_one_block_encryption() {
+    use std::cell::Cell;
+
+    struct CountingCipher {
+        inner: Aes128,
+        encryptions: Cell<usize>,
+    }
+
+    impl BlockCipher for CountingCipher {
+        const BLOCK_SIZE: usize = 16;
+
+        fn encrypt_block(&self, block: &mut [u8]) {
+            self.encryptions.set(self.encryptions.get() + 1);
+            self.inner.encrypt_block(block);
+        }
+
+        fn decrypt_block(&self, block: &mut [u8]) {
+            self.inner.decrypt_block(block);
+        }
+    }
+
+    let ctr = Ctr::new(CountingCipher {
+        inner: Aes128::new(b"YELLOW SUBMARINE"),
+        encryptions: Cell::new(0),
+    });
+
+    let offset = 1_000_003;
+    let mut keystream = ctr.keystream(&[0; 8]);
+    keystream.seek(offset);
+    let seeked: Vec<u8> = keystream.take(5).collect();
+
+    assert_eq!(ctr.cipher().encryptions.get(), 1);
+
+    let skipped: Vec<u8> = ctr
+        .keystream(&[0; 8])
+        .skip(offset as usize)
+        .take(5)
+        .collect();
+    assert_eq!(seeked, skipped);
+}
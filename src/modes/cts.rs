@@ -0,0 +1,213 @@
+use crate::block_cipher::BlockCipher;
+use crate::error::{Error, Result};
+
+use super::{or_panic, Cbc};
+
+// The three ways SP 800-38A's addendum lays out the last two blocks. CS1 keeps CBC's order (with
+// the next-to-last block truncated), CS2 swaps them only when the message isn't block-aligned
+// and CS3 (Kerberos, RFC 3962) always swaps them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CtsVariant {
+    Cs1,
+    Cs2,
+    Cs3,
+}
+
+// CBC with ciphertext stealing: no padding, so the ciphertext is as long as the plaintext, which
+// must still have at least one whole block
+pub struct CbcCts<C> {
+    cbc: Cbc<C>,
+    variant: CtsVariant,
+}
+
+impl<C: BlockCipher> CbcCts<C> {
+    pub fn new(cipher: C, variant: CtsVariant) -> Self {
+        Self {
+            cbc: Cbc::new(cipher),
+            variant,
+        }
+    }
+
+    pub fn variant(&self) -> CtsVariant {
+        self.variant
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8], iv: &[u8]) -> Vec<u8> {
+        or_panic(self.try_encrypt(plaintext, iv))
+    }
+
+    // Encrypts the zero-padded message with plain CBC, then drops the padding's worth of bytes
+    // from the next-to-last ciphertext block (which can be recovered by decrypting the last one)
+    pub fn try_encrypt(&self, plaintext: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+        let block_size = C::BLOCK_SIZE;
+        let tail_len = self.tail_len(plaintext)?;
+        let padded_len = plaintext.len() + block_size - tail_len;
+
+        let mut ciphertext = plaintext.to_vec();
+        ciphertext.resize(padded_len, 0);
+        self.cbc.try_encrypt_in_place(&mut ciphertext, iv)?;
+
+        if padded_len > block_size {
+            let last_block_start = padded_len - block_size;
+            ciphertext.drain(last_block_start - (block_size - tail_len)..last_block_start);
+
+            if self.swaps_last_blocks(tail_len) {
+                let len = ciphertext.len();
+                ciphertext[len - block_size - tail_len..].rotate_right(block_size);
+            }
+        }
+
+        Ok(ciphertext)
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8], iv: &[u8]) -> Vec<u8> {
+        or_panic(self.try_decrypt(ciphertext, iv))
+    }
+
+    pub fn try_decrypt(&self, ciphertext: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+        let block_size = C::BLOCK_SIZE;
+        let tail_len = self.tail_len(ciphertext)?;
+
+        if ciphertext.len() == block_size {
+            return self.cbc.try_decrypt_blocks(ciphertext, iv);
+        }
+
+        // Bring the blocks back to CS1 order: ..., C[n-1] (truncated), C[n]
+        let mut blocks = ciphertext.to_vec();
+        let stolen_start = blocks.len() - block_size - tail_len;
+        if self.swaps_last_blocks(tail_len) {
+            blocks[stolen_start..].rotate_left(block_size);
+        }
+
+        // C[n] was encrypted from C[n-1] xor the zero-padded last plaintext block, so decrypting
+        // it yields both the stolen bytes of C[n-1] and the last plaintext block
+        let mut last_block = blocks.split_off(stolen_start + tail_len);
+        self.cbc.cipher().decrypt_block(&mut last_block);
+
+        let last_plaintext: Vec<u8> = last_block[..tail_len]
+            .iter()
+            .zip(&blocks[stolen_start..])
+            .map(|(a, b)| a ^ b)
+            .collect();
+
+        blocks.extend_from_slice(&last_block[tail_len..]);
+        self.cbc.try_decrypt_in_place(&mut blocks, iv)?;
+
+        blocks.extend_from_slice(&last_plaintext);
+        Ok(blocks)
+    }
+
+    // How many bytes the last (possibly partial) block has
+    fn tail_len(&self, data: &[u8]) -> Result<usize> {
+        if data.len() < C::BLOCK_SIZE {
+            return Err(Error::InvalidLength {
+                expected: C::BLOCK_SIZE,
+                actual: data.len(),
+            });
+        }
+
+        Ok((data.len() - 1) % C::BLOCK_SIZE + 1)
+    }
+
+    fn swaps_last_blocks(&self, tail_len: usize) -> bool {
+        match self.variant {
+            CtsVariant::Cs1 => false,
+            CtsVariant::Cs2 => tail_len != C::BLOCK_SIZE,
+            CtsVariant::Cs3 => true,
+        }
+    }
+}
+
+#[cfg(test)]
+use crate::{
+    aes::Aes128,
+    encoding::{bytes_to_hex, hex_to_bytes},
+};
+
+#[test]
+fn test_cts_rfc3962_vectors() {
+    // RFC 3962 appendix B, which uses CS3 with a zero IV
+    let cts = CbcCts::new(Aes128::new(b"chicken teriyaki"), CtsVariant::Cs3);
+    let iv = [0; 16];
+    let plaintext = b"I would like the General Gau's Chicken, please, and wonton soup.";
+
+    let vectors = [
+        (17, "c6353568f2bf8cb4d8a580362da7ff7f97"),
+        (
+            31,
+            "fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5",
+        ),
+        (
+            32,
+            "39312523a78662d5be7fcbcc98ebf5a897687268d6ecccc0c07b25e25ecfe584",
+        ),
+        (
+            47,
+            "97687268d6ecccc0c07b25e25ecfe584b3fffd940c16a18c1b5549d2f838029e\
+             39312523a78662d5be7fcbcc98ebf5",
+        ),
+        (
+            48,
+            "97687268d6ecccc0c07b25e25ecfe5849dad8bbb96c4cdc03bc103e1a194bbd8\
+             39312523a78662d5be7fcbcc98ebf5a8",
+        ),
+        (
+            64,
+            "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8\
+             4807efe836ee89a526730dbc2f7bc8409dad8bbb96c4cdc03bc103e1a194bbd8",
+        ),
+    ];
+
+    for &(len, expected) in vectors.iter() {
+        let ciphertext = cts.encrypt(&plaintext[..len], &iv);
+        assert_eq!(bytes_to_hex(&ciphertext), expected);
+        assert_eq!(cts.decrypt(&ciphertext, &iv), &plaintext[..len]);
+    }
+}
+
+#[test]
+fn test_cts_variants_only_differ_in_block_order() {
+    let key = hex_to_bytes("2b7e151628aed2a6abf7158809cf4f3c");
+    let iv = [9; 16];
+    let plaintext: Vec<u8> = (0..70).collect();
+
+    let cs1 = CbcCts::new(Aes128::new(&key), CtsVariant::Cs1);
+    let cs2 = CbcCts::new(Aes128::new(&key), CtsVariant::Cs2);
+    let cs3 = CbcCts::new(Aes128::new(&key), CtsVariant::Cs3);
+    let cbc = Cbc::new(Aes128::new(&key));
+
+    // Block-aligned: CS1 and CS2 are plain CBC, CS3 swaps the last two blocks
+    let aligned = &plaintext[..64];
+    let cbc_ciphertext = cbc.encrypt_blocks(aligned, &iv);
+    assert_eq!(cs1.encrypt(aligned, &iv), cbc_ciphertext);
+    assert_eq!(cs2.encrypt(aligned, &iv), cbc_ciphertext);
+
+    let mut swapped = cbc_ciphertext;
+    swapped[32..].rotate_left(16);
+    assert_eq!(cs3.encrypt(aligned, &iv), swapped);
+
+    // Unaligned: CS2 and CS3 agree, and CS1 has the same blocks with the last two swapped
+    let cs1_ciphertext = cs1.encrypt(&plaintext, &iv);
+    let cs2_ciphertext = cs2.encrypt(&plaintext, &iv);
+    assert_eq!(cs2_ciphertext, cs3.encrypt(&plaintext, &iv));
+
+    let mut reordered = cs1_ciphertext.clone();
+    reordered[48..].rotate_right(16);
+    assert_eq!(reordered, cs2_ciphertext);
+
+    for cts in [&cs1, &cs2, &cs3].iter() {
+        for len in 16..70 {
+            let ciphertext = cts.encrypt(&plaintext[..len], &iv);
+            assert_eq!(ciphertext.len(), len);
+            assert_eq!(cts.decrypt(&ciphertext, &iv), &plaintext[..len]);
+        }
+    }
+
+    assert_eq!(
+        cs1.try_encrypt(&plaintext[..15], &iv),
+        Err(Error::InvalidLength {
+            expected: 16,
+            actual: 15
+        })
+    );
+}
@@ -0,0 +1,55 @@
+use crate::block_cipher::BlockCipher;
+use crate::error::Result;
+use crate::padding::{Padding, Pkcs7};
+
+use super::or_panic;
+
+pub struct Ecb<C, P = Pkcs7> {
+    cipher: C,
+    padding: P,
+}
+
+impl<C: BlockCipher> Ecb<C> {
+    pub fn new(cipher: C) -> Self {
+        Self::with_padding(cipher, Pkcs7)
+    }
+}
+
+impl<C: BlockCipher, P: Padding> Ecb<C, P> {
+    pub fn with_padding(cipher: C, padding: P) -> Self {
+        Self { cipher, padding }
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        self.encrypt_blocks(&self.padding.pad(plaintext, C::BLOCK_SIZE))
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        self.padding
+            .unpad(&self.try_decrypt_blocks(ciphertext)?, C::BLOCK_SIZE)
+    }
+
+    pub fn encrypt_blocks(&self, plaintext: &[u8]) -> Vec<u8> {
+        or_panic(self.try_encrypt_blocks(plaintext))
+    }
+
+    pub fn try_encrypt_blocks(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        super::check_block_alignment(plaintext, C::BLOCK_SIZE)?;
+
+        let mut ciphertext = plaintext.to_vec();
+        self.cipher.encrypt_blocks(&mut ciphertext);
+        Ok(ciphertext)
+    }
+
+    pub fn decrypt_blocks(&self, ciphertext: &[u8]) -> Vec<u8> {
+        or_panic(self.try_decrypt_blocks(ciphertext))
+    }
+
+    pub fn try_decrypt_blocks(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        super::check_block_alignment(ciphertext, C::BLOCK_SIZE)?;
+
+        let mut plaintext = ciphertext.to_vec();
+        self.cipher.decrypt_blocks(&mut plaintext);
+        Ok(plaintext)
+    }
+}
@@ -0,0 +1,288 @@
+use crate::block_cipher::BlockCipher;
+use crate::bytes::constant_time_eq;
+use crate::error::{Error, Result};
+use crate::gf128::Gf128;
+
+use super::{Ctr, CtrParams};
+
+const GCM_BLOCK_SIZE: usize = 16;
+const DEFAULT_TAG_LEN: usize = 16;
+
+pub struct Gcm<C> {
+    ctr: Ctr<C>,
+    hash_key: Gf128,
+    tag_len: usize,
+}
+
+impl<C: BlockCipher> Gcm<C> {
+    pub fn new(cipher: C) -> Self {
+        Self::with_tag_len(cipher, DEFAULT_TAG_LEN)
+    }
+
+    // SP 800-38D allows tags of 128, 120, 112, 104 or 96 bits, plus 64 and 32 bits for some
+    // applications
+    pub fn with_tag_len(cipher: C, tag_len: usize) -> Self {
+        if C::BLOCK_SIZE != GCM_BLOCK_SIZE {
+            panic!("GCM requires a cipher with 128-bit blocks");
+        }
+
+        if ![4, 8, 12, 13, 14, 15, 16].contains(&tag_len) {
+            panic!("Invalid GCM tag length: {}", tag_len);
+        }
+
+        let mut hash_key = [0; GCM_BLOCK_SIZE];
+        cipher.encrypt_block(&mut hash_key);
+
+        Self {
+            ctr: Ctr::with_params(cipher, CtrParams::GCM),
+            hash_key: Gf128::from_bytes(&hash_key),
+            tag_len,
+        }
+    }
+
+    pub fn hash_key(&self) -> Gf128 {
+        self.hash_key
+    }
+
+    pub fn encrypt(
+        &self,
+        iv: &[u8],
+        associated_data: &[u8],
+        plaintext: &[u8],
+    ) -> (Vec<u8>, Vec<u8>) {
+        let initial_counter = self.initial_counter(iv);
+
+        let mut ciphertext = plaintext.to_vec();
+        self.apply_keystream(&initial_counter, &mut ciphertext);
+
+        let tag = self.compute_tag(&initial_counter, associated_data, &ciphertext);
+
+        (ciphertext, tag)
+    }
+
+    pub fn decrypt(
+        &self,
+        iv: &[u8],
+        associated_data: &[u8],
+        ciphertext: &[u8],
+        tag: &[u8],
+    ) -> Result<Vec<u8>> {
+        let initial_counter = self.initial_counter(iv);
+        let expected_tag = self.compute_tag(&initial_counter, associated_data, ciphertext);
+
+        if !constant_time_eq(&expected_tag, tag) {
+            return Err(Error::BadTag);
+        }
+
+        let mut plaintext = ciphertext.to_vec();
+        self.apply_keystream(&initial_counter, &mut plaintext);
+
+        Ok(plaintext)
+    }
+
+    fn initial_counter(&self, iv: &[u8]) -> [u8; GCM_BLOCK_SIZE] {
+        let mut counter = [0; GCM_BLOCK_SIZE];
+
+        if iv.len() == 12 {
+            counter[..12].copy_from_slice(iv);
+            counter[15] = 1;
+        } else {
+            let hash = ghash(self.hash_key, &[], iv);
+            counter.copy_from_slice(&hash.to_bytes());
+        }
+
+        counter
+    }
+
+    fn compute_tag(
+        &self,
+        initial_counter: &[u8; GCM_BLOCK_SIZE],
+        associated_data: &[u8],
+        ciphertext: &[u8],
+    ) -> Vec<u8> {
+        let mut tag = ghash(self.hash_key, associated_data, ciphertext)
+            .to_bytes()
+            .to_vec();
+
+        // The tag is masked with the keystream block for the initial counter itself
+        self.ctr.keystream(initial_counter).apply(&mut tag);
+        tag.truncate(self.tag_len);
+        tag
+    }
+
+    // The message is encrypted starting from the block after the initial counter
+    fn apply_keystream(&self, initial_counter: &[u8; GCM_BLOCK_SIZE], data: &mut [u8]) {
+        let mut keystream = self.ctr.keystream(initial_counter);
+        keystream.seek(GCM_BLOCK_SIZE as u64);
+        keystream.apply(data);
+    }
+}
+
+// GHASH over the associated data and ciphertext (each zero-padded to a whole number of blocks),
+// followed by the block with their lengths in bits
+pub fn ghash(hash_key: Gf128, associated_data: &[u8], ciphertext: &[u8]) -> Gf128 {
+    let mut length_block = [0; GCM_BLOCK_SIZE];
+    length_block[..8].copy_from_slice(&((associated_data.len() as u64) * 8).to_be_bytes());
+    length_block[8..].copy_from_slice(&((ciphertext.len() as u64) * 8).to_be_bytes());
+
+    let blocks = associated_data
+        .chunks(GCM_BLOCK_SIZE)
+        .chain(ciphertext.chunks(GCM_BLOCK_SIZE))
+        .chain(Some(&length_block[..]));
+
+    let mut result = Gf128::ZERO;
+    for block in blocks {
+        let mut padded = [0; GCM_BLOCK_SIZE];
+        padded[..block.len()].copy_from_slice(block);
+
+        result = (result + Gf128::from_bytes(&padded)) * hash_key;
+    }
+
+    result
+}
+
+#[cfg(test)]
+use crate::{
+    aes::Aes128,
+    encoding::{bytes_to_hex, hex_to_bytes},
+};
+
+#[cfg(test)]
+const TEST_KEY: &str = "feffe9928665731c6d6a8f9467308308";
+
+#[cfg(test)]
+const TEST_PLAINTEXT: &str = "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+                              1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39";
+
+#[cfg(test)]
+const TEST_ASSOCIATED_DATA: &str = "feedfacedeadbeeffeedfacedeadbeefabaddad2";
+
+#[cfg(test)]
+fn check_vector(
+    key: &str,
+    iv: &str,
+    plaintext: &str,
+    associated_data: &str,
+    expected_ciphertext: &str,
+    expected_tag: &str,
+) {
+    let gcm = Gcm::new(Aes128::new(&hex_to_bytes(key)));
+    let (iv, plaintext, associated_data) = (
+        hex_to_bytes(iv),
+        hex_to_bytes(plaintext),
+        hex_to_bytes(associated_data),
+    );
+
+    let (ciphertext, tag) = gcm.encrypt(&iv, &associated_data, &plaintext);
+    assert_eq!(bytes_to_hex(&ciphertext), expected_ciphertext);
+    assert_eq!(bytes_to_hex(&tag), expected_tag);
+
+    let decrypted = gcm.decrypt(&iv, &associated_data, &ciphertext, &tag);
+    assert_eq!(decrypted, Ok(plaintext));
+}
+
+#[test]
+fn test_gcm_zero_key_vectors() {
+    let zero_key = "00000000000000000000000000000000";
+    let zero_iv = "000000000000000000000000";
+
+    check_vector(
+        zero_key,
+        zero_iv,
+        "",
+        "",
+        "",
+        "58e2fccefa7e3061367f1d57a4e7455a",
+    );
+    check_vector(
+        zero_key,
+        zero_iv,
+        zero_key,
+        "",
+        "0388dace60b6a392f328c2b971b2fe78",
+        "ab6e47d42cec13bdf53a67b21257bddf",
+    );
+}
+
+#[test]
+fn test_gcm_96_bit_iv_with_associated_data() {
+    check_vector(
+        TEST_KEY,
+        "cafebabefacedbaddecaf888",
+        TEST_PLAINTEXT,
+        TEST_ASSOCIATED_DATA,
+        "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+         21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091",
+        "5bc94fbc3221a5db94fae95ae7121a47",
+    );
+}
+
+#[test]
+fn test_gcm_arbitrary_length_ivs() {
+    check_vector(
+        TEST_KEY,
+        "cafebabefacedbad",
+        TEST_PLAINTEXT,
+        TEST_ASSOCIATED_DATA,
+        "61353b4c2806934a777ff51fa22a4755699b2a714fcdc6f83766e5f97b6c7423\
+         73806900e49f24b22b097544d4896b424989b5e1ebac0f07c23f4598",
+        "3612d2e79e3b0785561be14aaca2fccb",
+    );
+    check_vector(
+        TEST_KEY,
+        "9313225df88406e555909c5aff5269aa6a7a9538534f7da1e4c303d2a318a728\
+         c3c0c95156809539fcf0e2429a6b525416aedbf5a0de6a57a637b39b",
+        TEST_PLAINTEXT,
+        TEST_ASSOCIATED_DATA,
+        "8ce24998625615b603a033aca13fb894be9112a5c3a211a8ba262a3cca7e2ca7\
+         01e4a9a4fba43c90ccdcb281d48c7c6fd62875d2aca417034c34aee5",
+        "619cc5aefffe0bfa462af43c1699d050",
+    );
+}
+
+#[test]
+fn test_gcm_truncated_tag_and_forgery() {
+    let gcm = Gcm::with_tag_len(Aes128::new(&hex_to_bytes(TEST_KEY)), 12);
+    let iv = [7; 12];
+
+    let (mut ciphertext, tag) = gcm.encrypt(&iv, b"header", b"attack at dawn");
+    assert_eq!(tag.len(), 12);
+    assert_eq!(
+        gcm.decrypt(&iv, b"header", &ciphertext, &tag),
+        Ok(b"attack at dawn".to_vec())
+    );
+
+    assert_eq!(
+        gcm.decrypt(&iv, b"footer", &ciphertext, &tag),
+        Err(Error::BadTag)
+    );
+
+    ciphertext[0] ^= 1;
+    assert_eq!(
+        gcm.decrypt(&iv, b"header", &ciphertext, &tag),
+        Err(Error::BadTag)
+    );
+}
+
+#[test]
+fn test_gcm_nonce_reuse_recovers_hash_key() {
+    use crate::gf128::Polynomial;
+
+    let gcm = Gcm::new(Aes128::new(b"YELLOW SUBMARINE"));
+    let iv = [1; 12];
+
+    let (ciphertext1, tag1) = gcm.encrypt(&iv, &[], b"thirty-two bytes of plaintext!!!");
+    let (ciphertext2, tag2) = gcm.encrypt(&iv, &[], b"another 32 bytes of plaintext...");
+
+    // tag = C1 * H^3 + C2 * H^2 + L * H + E(K, J0), so adding both tags cancels out the parts
+    // that don't depend on the ciphertext and leaves a polynomial with H as one of its roots
+    let block = |bytes: &[u8]| Gf128::from_bytes(bytes);
+    let difference = Polynomial::new(vec![
+        block(&tag1) + block(&tag2),
+        Gf128::ZERO,
+        block(&ciphertext1[16..]) + block(&ciphertext2[16..]),
+        block(&ciphertext1[..16]) + block(&ciphertext2[..16]),
+    ]);
+
+    assert!(difference.roots().contains(&gcm.hash_key()));
+}
@@ -0,0 +1,77 @@
+use crate::block_cipher::BlockCipher;
+use crate::error::Result;
+
+use super::{or_panic, xor_in_place};
+
+// Output feedback mode: the keystream is the IV encrypted over and over, so (like CTR) encryption
+// and decryption are the same operation and bit flips only affect the bits they land on
+pub struct Ofb<C> {
+    cipher: C,
+}
+
+impl<C: BlockCipher> Ofb<C> {
+    pub fn new(cipher: C) -> Self {
+        Self { cipher }
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8], iv: &[u8]) -> Vec<u8> {
+        or_panic(self.try_encrypt(plaintext, iv))
+    }
+
+    pub fn try_encrypt(&self, plaintext: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+        let mut ciphertext = plaintext.to_vec();
+        self.try_apply_keystream(&mut ciphertext, iv)?;
+        Ok(ciphertext)
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8], iv: &[u8]) -> Vec<u8> {
+        or_panic(self.try_decrypt(ciphertext, iv))
+    }
+
+    pub fn try_decrypt(&self, ciphertext: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+        let mut plaintext = ciphertext.to_vec();
+        self.try_apply_keystream(&mut plaintext, iv)?;
+        Ok(plaintext)
+    }
+
+    pub fn apply_keystream(&self, data: &mut [u8], iv: &[u8]) {
+        or_panic(self.try_apply_keystream(data, iv))
+    }
+
+    pub fn try_apply_keystream(&self, data: &mut [u8], iv: &[u8]) -> Result<()> {
+        super::check_iv_len(iv, C::BLOCK_SIZE)?;
+
+        let mut keystream_block = iv.to_vec();
+
+        for chunk in data.chunks_mut(C::BLOCK_SIZE) {
+            self.cipher.encrypt_block(&mut keystream_block);
+            xor_in_place(chunk, &keystream_block);
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_ofb_nist_vector() {
+    use crate::aes::Aes128;
+    use crate::encoding::{bytes_to_hex, hex_to_bytes};
+
+    // SP 800-38A F.4.1 (OFB-AES128.Encrypt)
+    let ofb = Ofb::new(Aes128::new(&hex_to_bytes(
+        "2b7e151628aed2a6abf7158809cf4f3c",
+    )));
+    let iv = hex_to_bytes("000102030405060708090a0b0c0d0e0f");
+    let plaintext = hex_to_bytes(
+        "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+         30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+    );
+
+    let ciphertext = ofb.encrypt(&plaintext, &iv);
+    assert_eq!(
+        bytes_to_hex(&ciphertext),
+        "3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac54ed825\
+         9740051e9c5fecf64344f7a82260edcc304c6528f659c77866a510d9c1d6ae5e"
+    );
+    assert_eq!(ofb.decrypt(&ciphertext, &iv), plaintext);
+}
@@ -0,0 +1,127 @@
+use crate::block_cipher::BlockCipher;
+use crate::error::Result;
+use crate::padding::{Padding, Pkcs7};
+
+use super::{or_panic, xor_in_place};
+
+// Propagating CBC: each block is chained with both the previous plaintext and ciphertext blocks,
+// so corrupting one ciphertext block garbles every block after it. Swapping two adjacent
+// ciphertext blocks, however, leaves everything after them intact
+pub struct Pcbc<C, P = Pkcs7> {
+    cipher: C,
+    padding: P,
+}
+
+impl<C: BlockCipher> Pcbc<C> {
+    pub fn new(cipher: C) -> Self {
+        Self::with_padding(cipher, Pkcs7)
+    }
+}
+
+impl<C: BlockCipher, P: Padding> Pcbc<C, P> {
+    pub fn with_padding(cipher: C, padding: P) -> Self {
+        Self { cipher, padding }
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8], iv: &[u8]) -> Vec<u8> {
+        or_panic(self.try_encrypt(plaintext, iv))
+    }
+
+    pub fn try_encrypt(&self, plaintext: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+        self.try_encrypt_blocks(&self.padding.pad(plaintext, C::BLOCK_SIZE), iv)
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+        self.padding
+            .unpad(&self.try_decrypt_blocks(ciphertext, iv)?, C::BLOCK_SIZE)
+    }
+
+    pub fn encrypt_blocks(&self, plaintext: &[u8], iv: &[u8]) -> Vec<u8> {
+        or_panic(self.try_encrypt_blocks(plaintext, iv))
+    }
+
+    pub fn try_encrypt_blocks(&self, plaintext: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+        super::check_iv_len(iv, C::BLOCK_SIZE)?;
+        super::check_block_alignment(plaintext, C::BLOCK_SIZE)?;
+
+        let mut ciphertext = plaintext.to_vec();
+        let mut chain = iv.to_vec();
+
+        for (block, plaintext_block) in ciphertext
+            .chunks_mut(C::BLOCK_SIZE)
+            .zip(plaintext.chunks(C::BLOCK_SIZE))
+        {
+            xor_in_place(block, &chain);
+            self.cipher.encrypt_block(block);
+
+            chain.copy_from_slice(plaintext_block);
+            xor_in_place(&mut chain, block);
+        }
+
+        Ok(ciphertext)
+    }
+
+    pub fn decrypt_blocks(&self, ciphertext: &[u8], iv: &[u8]) -> Vec<u8> {
+        or_panic(self.try_decrypt_blocks(ciphertext, iv))
+    }
+
+    pub fn try_decrypt_blocks(&self, ciphertext: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+        super::check_iv_len(iv, C::BLOCK_SIZE)?;
+        super::check_block_alignment(ciphertext, C::BLOCK_SIZE)?;
+
+        let mut plaintext = ciphertext.to_vec();
+        let mut chain = iv.to_vec();
+
+        for (block, ciphertext_block) in plaintext
+            .chunks_mut(C::BLOCK_SIZE)
+            .zip(ciphertext.chunks(C::BLOCK_SIZE))
+        {
+            self.cipher.decrypt_block(block);
+            xor_in_place(block, &chain);
+
+            chain.copy_from_slice(ciphertext_block);
+            xor_in_place(&mut chain, block);
+        }
+
+        Ok(plaintext)
+    }
+}
+
+#[cfg(test)]
+use crate::aes::Aes128;
+
+#[test]
+fn test_pcbc_roundtrip() {
+    let pcbc = Pcbc::new(Aes128::new(b"YELLOW SUBMARINE"));
+    let iv = [3; 16];
+
+    for len in 0..50 {
+        let plaintext: Vec<u8> = (0..len as u8).collect();
+        let ciphertext = pcbc.encrypt(&plaintext, &iv);
+
+        assert_eq!(pcbc.decrypt(&ciphertext, &iv), Ok(plaintext));
+    }
+}
+
+#[test]
+fn test_pcbc_error_propagation() {
+    let pcbc = Pcbc::new(Aes128::new(b"YELLOW SUBMARINE"));
+    let iv = [0; 16];
+    let plaintext = [0; 64];
+    let ciphertext = pcbc.encrypt_blocks(&plaintext, &iv);
+
+    let mut corrupted = ciphertext.clone();
+    corrupted[20] ^= 1;
+    let decrypted = pcbc.decrypt_blocks(&corrupted, &iv);
+    assert_eq!(&decrypted[..16], &plaintext[..16]);
+    assert!(decrypted[16..]
+        .chunks(16)
+        .all(|block| block != &plaintext[..16]));
+
+    // P_i xor C_i is what gets chained, and swapping two blocks keeps the xor of both pairs
+    let mut swapped = ciphertext;
+    swapped[16..48].rotate_left(16);
+    let decrypted = pcbc.decrypt_blocks(&swapped, &iv);
+    assert_ne!(&decrypted[16..48], &plaintext[16..48]);
+    assert_eq!(&decrypted[48..], &plaintext[48..]);
+}
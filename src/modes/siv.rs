@@ -0,0 +1,177 @@
+use crate::block_cipher::BlockCipher;
+use crate::bytes::constant_time_eq;
+use crate::cmac::{dbl, Cmac};
+use crate::error::{Error, Result};
+
+use super::{xor_in_place, Ctr, CtrParams};
+
+const SIV_BLOCK_SIZE: usize = 16;
+
+// SIV (RFC 5297): the synthetic IV is a MAC (S2V over CMAC) of the associated data and the
+// plaintext, and it doubles as the CTR IV. Encryption is deterministic, so reusing a nonce (or
+// not having one at all) only reveals whether the same message was encrypted twice. A nonce, if
+// used, is just the last associated data component
+pub struct Siv<C> {
+    cmac: Cmac<C>,
+    ctr: Ctr<C>,
+}
+
+impl<C: BlockCipher> Siv<C> {
+    // RFC 5297 keys are the concatenation of the MAC key and the CTR key
+    pub fn new(mac_cipher: C, ctr_cipher: C) -> Self {
+        if C::BLOCK_SIZE != SIV_BLOCK_SIZE {
+            panic!("SIV requires a cipher with 128-bit blocks");
+        }
+
+        Self {
+            cmac: Cmac::new(mac_cipher),
+            ctr: Ctr::with_params(ctr_cipher, CtrParams::FULL_BLOCK),
+        }
+    }
+
+    // Returns the synthetic IV followed by the ciphertext
+    pub fn encrypt(&self, associated_data: &[&[u8]], plaintext: &[u8]) -> Vec<u8> {
+        let siv = self.s2v(associated_data, plaintext);
+
+        let mut result = siv.clone();
+        result.extend_from_slice(plaintext);
+        self.apply_keystream(&siv, &mut result[SIV_BLOCK_SIZE..]);
+
+        result
+    }
+
+    pub fn decrypt(&self, associated_data: &[&[u8]], ciphertext: &[u8]) -> Result<Vec<u8>> {
+        if ciphertext.len() < SIV_BLOCK_SIZE {
+            return Err(Error::InvalidLength {
+                expected: SIV_BLOCK_SIZE,
+                actual: ciphertext.len(),
+            });
+        }
+
+        let (siv, ciphertext) = ciphertext.split_at(SIV_BLOCK_SIZE);
+
+        let mut plaintext = ciphertext.to_vec();
+        self.apply_keystream(siv, &mut plaintext);
+
+        if !constant_time_eq(&self.s2v(associated_data, &plaintext), siv) {
+            return Err(Error::BadTag);
+        }
+
+        Ok(plaintext)
+    }
+
+    // Turns a vector of strings into a single MAC, so that the boundaries between them matter
+    fn s2v(&self, associated_data: &[&[u8]], plaintext: &[u8]) -> Vec<u8> {
+        let mut d = self.cmac.compute(&[0; SIV_BLOCK_SIZE]);
+
+        for component in associated_data {
+            dbl(&mut d);
+            xor_in_place(&mut d, &self.cmac.compute(component));
+        }
+
+        let t = if plaintext.len() >= SIV_BLOCK_SIZE {
+            // xorend: D goes into the last 16 bytes of the plaintext
+            let mut t = plaintext.to_vec();
+            xor_in_place(&mut t[plaintext.len() - SIV_BLOCK_SIZE..], &d);
+            t
+        } else {
+            let mut t = plaintext.to_vec();
+            t.push(0x80);
+            t.resize(SIV_BLOCK_SIZE, 0);
+
+            dbl(&mut d);
+            xor_in_place(&mut t, &d);
+            t
+        };
+
+        self.cmac.compute(&t)
+    }
+
+    // The counter starts from the SIV with the top bit of its last two 32-bit words cleared, so
+    // implementations can get away with 64-bit (or even 32-bit) counter arithmetic
+    fn apply_keystream(&self, siv: &[u8], data: &mut [u8]) {
+        let mut counter = siv.to_vec();
+        counter[8] &= 0x7f;
+        counter[12] &= 0x7f;
+
+        self.ctr.keystream(&counter).apply(data);
+    }
+}
+
+#[cfg(test)]
+use crate::{
+    aes::Aes128,
+    encoding::{bytes_to_hex, hex_to_bytes},
+};
+
+#[cfg(test)]
+fn siv_from_hex(key: &str) -> Siv<Aes128> {
+    let key = hex_to_bytes(key);
+    Siv::new(Aes128::new(&key[..16]), Aes128::new(&key[16..]))
+}
+
+#[test]
+fn test_siv_rfc5297_deterministic_vector() {
+    // RFC 5297 A.1
+    let siv = siv_from_hex("fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+    let associated_data = hex_to_bytes("101112131415161718191a1b1c1d1e1f2021222324252627");
+    let plaintext = hex_to_bytes("112233445566778899aabbccddee");
+
+    let ciphertext = siv.encrypt(&[&associated_data], &plaintext);
+    assert_eq!(
+        bytes_to_hex(&ciphertext),
+        "85632d07c6e8f37f950acd320a2ecc9340c02b9690c4dc04daef7f6afe5c"
+    );
+    assert_eq!(siv.decrypt(&[&associated_data], &ciphertext), Ok(plaintext));
+}
+
+#[test]
+fn test_siv_rfc5297_nonce_based_vector() {
+    // RFC 5297 A.2, where the nonce is the last associated data component
+    let siv = siv_from_hex("7f7e7d7c7b7a79787776757473727170404142434445464748494a4b4c4d4e4f");
+    let associated_data = [
+        hex_to_bytes(
+            "00112233445566778899aabbccddeeffdeaddadadeaddadaffeeddccbbaa9988\
+             7766554433221100",
+        ),
+        hex_to_bytes("102030405060708090a0"),
+        hex_to_bytes("09f911029d74e35bd84156c5635688c0"),
+    ];
+    let associated_data: Vec<&[u8]> = associated_data.iter().map(Vec::as_slice).collect();
+    let plaintext = b"this is some plaintext to encrypt using SIV-AES";
+
+    let ciphertext = siv.encrypt(&associated_data, plaintext);
+    assert_eq!(
+        bytes_to_hex(&ciphertext),
+        "7bdb6e3b432667eb06f4d14bff2fbd0fcb900f2fddbe404326601965c889bf17\
+         dba77ceb094fa663b7a3f748ba8af829ea64ad544a272e9c485b62a3fd5c0d"
+    );
+    assert_eq!(
+        siv.decrypt(&associated_data, &ciphertext),
+        Ok(plaintext.to_vec())
+    );
+}
+
+#[test]
+fn test_siv_rejects_tampering() {
+    let siv = Siv::new(
+        Aes128::new(b"YELLOW SUBMARINE"),
+        Aes128::new(b"PURPLE SUBMARINE"),
+    );
+
+    let mut ciphertext = siv.encrypt(&[b"header"], b"attack at dawn");
+    assert_eq!(
+        siv.decrypt(&[b"header"], &ciphertext[..10]),
+        Err(Error::InvalidLength {
+            expected: 16,
+            actual: 10
+        })
+    );
+    assert_eq!(
+        siv.decrypt(&[b"header", b""], &ciphertext),
+        Err(Error::BadTag)
+    );
+
+    ciphertext[20] ^= 1;
+    assert_eq!(siv.decrypt(&[b"header"], &ciphertext), Err(Error::BadTag));
+}
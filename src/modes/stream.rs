@@ -0,0 +1,463 @@
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::block_cipher::BlockCipher;
+use crate::error::Result;
+use crate::padding::{Padding, Pkcs7};
+
+use super::{or_panic, Cbc, Ctr};
+
+const READ_CHUNK_SIZE: usize = 4096;
+
+pub struct CbcEncryptor<W, C, P = Pkcs7> {
+    inner: W,
+    cbc: Cbc<C, P>,
+    previous_block: Vec<u8>,
+    pending: Vec<u8>,
+}
+
+impl<W: Write, C: BlockCipher> CbcEncryptor<W, C> {
+    pub fn new(inner: W, cipher: C, iv: &[u8]) -> Self {
+        or_panic(Self::try_new(inner, cipher, iv))
+    }
+
+    pub fn try_new(inner: W, cipher: C, iv: &[u8]) -> Result<Self> {
+        Self::try_with_cbc(inner, Cbc::new(cipher), iv)
+    }
+}
+
+impl<W: Write, C: BlockCipher, P: Padding> CbcEncryptor<W, C, P> {
+    pub fn with_cbc(inner: W, cbc: Cbc<C, P>, iv: &[u8]) -> Self {
+        or_panic(Self::try_with_cbc(inner, cbc, iv))
+    }
+
+    pub fn try_with_cbc(inner: W, cbc: Cbc<C, P>, iv: &[u8]) -> Result<Self> {
+        super::check_iv_len(iv, C::BLOCK_SIZE)?;
+
+        Ok(Self {
+            inner,
+            cbc,
+            previous_block: iv.to_vec(),
+            pending: Vec::with_capacity(C::BLOCK_SIZE),
+        })
+    }
+
+    // Pads whatever is left over and writes the final block(s). Dropping the encryptor without
+    // calling this leaves the ciphertext truncated
+    pub fn finish(mut self) -> io::Result<W> {
+        let last_blocks = self.cbc.padding().pad(&self.pending, C::BLOCK_SIZE);
+        self.encrypt_and_write(last_blocks)?;
+        self.inner.flush()?;
+
+        Ok(self.inner)
+    }
+
+    // The chain only moves on once the ciphertext is out, so a failed write leaves the encryptor
+    // as it was
+    fn encrypt_and_write(&mut self, mut blocks: Vec<u8>) -> io::Result<()> {
+        self.cbc.encrypt_in_place(&mut blocks, &self.previous_block);
+        self.inner.write_all(&blocks)?;
+
+        self.previous_block
+            .copy_from_slice(&blocks[blocks.len() - C::BLOCK_SIZE..]);
+        Ok(())
+    }
+}
+
+impl<W: Write, C: BlockCipher, P: Padding> Write for CbcEncryptor<W, C, P> {
+    // Nothing is taken from `buf` unless it all makes it to the inner writer, so that an error
+    // really means none of it was consumed
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let total_len = self.pending.len() + buf.len();
+        let complete_len = total_len - total_len % C::BLOCK_SIZE;
+
+        if complete_len == 0 {
+            self.pending.extend_from_slice(buf);
+            return Ok(buf.len());
+        }
+
+        let consumed = complete_len - self.pending.len();
+        let mut blocks = Vec::with_capacity(complete_len);
+        blocks.extend_from_slice(&self.pending);
+        blocks.extend_from_slice(&buf[..consumed]);
+        self.encrypt_and_write(blocks)?;
+
+        self.pending.clear();
+        self.pending.extend_from_slice(&buf[consumed..]);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub struct CbcDecryptor<R, C, P = Pkcs7> {
+    inner: R,
+    cbc: Cbc<C, P>,
+    previous_block: Vec<u8>,
+    pending: Vec<u8>,
+    plaintext: Vec<u8>,
+    plaintext_position: usize,
+    finished: bool,
+}
+
+impl<R: Read, C: BlockCipher> CbcDecryptor<R, C> {
+    pub fn new(inner: R, cipher: C, iv: &[u8]) -> Self {
+        or_panic(Self::try_new(inner, cipher, iv))
+    }
+
+    pub fn try_new(inner: R, cipher: C, iv: &[u8]) -> Result<Self> {
+        Self::try_with_cbc(inner, Cbc::new(cipher), iv)
+    }
+}
+
+impl<R: Read, C: BlockCipher, P: Padding> CbcDecryptor<R, C, P> {
+    pub fn with_cbc(inner: R, cbc: Cbc<C, P>, iv: &[u8]) -> Self {
+        or_panic(Self::try_with_cbc(inner, cbc, iv))
+    }
+
+    pub fn try_with_cbc(inner: R, cbc: Cbc<C, P>, iv: &[u8]) -> Result<Self> {
+        super::check_iv_len(iv, C::BLOCK_SIZE)?;
+
+        Ok(Self {
+            inner,
+            cbc,
+            previous_block: iv.to_vec(),
+            pending: Vec::with_capacity(READ_CHUNK_SIZE + C::BLOCK_SIZE),
+            plaintext: Vec::with_capacity(READ_CHUNK_SIZE),
+            plaintext_position: 0,
+            finished: false,
+        })
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn fill_plaintext(&mut self) -> io::Result<()> {
+        let mut chunk = [0; READ_CHUNK_SIZE];
+        let count = self.inner.read(&mut chunk)?;
+        self.pending.extend_from_slice(&chunk[..count]);
+
+        let end_of_stream = count == 0;
+
+        // The last block can only be decrypted once we know it's the last one, since that's
+        // where the padding lives
+        let decryptable_len = if end_of_stream {
+            if self.pending.is_empty() || !self.pending.len().is_multiple_of(C::BLOCK_SIZE) {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Ciphertext isn't a whole number of blocks",
+                ));
+            }
+
+            self.pending.len()
+        } else if self.pending.len() > C::BLOCK_SIZE {
+            (self.pending.len() - 1) / C::BLOCK_SIZE * C::BLOCK_SIZE
+        } else {
+            0
+        };
+
+        let mut blocks: Vec<u8> = self.pending.drain(..decryptable_len).collect();
+        if !blocks.is_empty() {
+            let last_ciphertext_block = blocks[blocks.len() - C::BLOCK_SIZE..].to_vec();
+            self.cbc.decrypt_in_place(&mut blocks, &self.previous_block);
+            self.previous_block = last_ciphertext_block;
+        }
+
+        if end_of_stream {
+            blocks = self.cbc.padding().unpad(&blocks, C::BLOCK_SIZE)?;
+            self.finished = true;
+        }
+
+        self.plaintext = blocks;
+        self.plaintext_position = 0;
+
+        Ok(())
+    }
+}
+
+impl<R: Read, C: BlockCipher, P: Padding> Read for CbcDecryptor<R, C, P> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.plaintext_position == self.plaintext.len() {
+            if self.finished {
+                return Ok(0);
+            }
+
+            self.fill_plaintext()?;
+        }
+
+        let available = &self.plaintext[self.plaintext_position..];
+        let count = available.len().min(buf.len());
+
+        buf[..count].copy_from_slice(&available[..count]);
+        self.plaintext_position += count;
+
+        Ok(count)
+    }
+}
+
+// Since CTR encryption and decryption are the same operation, the same type works as a reader
+// (transforming what comes from the inner stream) and as a writer (transforming what goes into it).
+// Seeking the inner stream also seeks the keystream, so random access is cheap
+pub struct CtrStream<S, C> {
+    inner: S,
+    ctr: Ctr<C>,
+    nonce: Vec<u8>,
+    position: u64,
+}
+
+impl<S, C: BlockCipher> CtrStream<S, C> {
+    pub fn new(inner: S, cipher: C, nonce: &[u8]) -> Self {
+        Self::with_ctr(inner, Ctr::new(cipher), nonce)
+    }
+
+    pub fn try_new(inner: S, cipher: C, nonce: &[u8]) -> Result<Self> {
+        Self::try_with_ctr(inner, Ctr::new(cipher), nonce)
+    }
+
+    pub fn with_ctr(inner: S, ctr: Ctr<C>, nonce: &[u8]) -> Self {
+        or_panic(Self::try_with_ctr(inner, ctr, nonce))
+    }
+
+    pub fn try_with_ctr(inner: S, ctr: Ctr<C>, nonce: &[u8]) -> Result<Self> {
+        // validate the nonce up front instead of on the first read/write
+        ctr.try_keystream(nonce)?;
+
+        Ok(Self {
+            inner,
+            ctr,
+            nonce: nonce.to_vec(),
+            position: 0,
+        })
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn apply_keystream(&mut self, data: &mut [u8]) {
+        let mut keystream = self.ctr.keystream(&self.nonce);
+        keystream.seek(self.position);
+        keystream.apply(data);
+
+        self.position = keystream.position();
+    }
+}
+
+impl<S: Read, C: BlockCipher> Read for CtrStream<S, C> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.apply_keystream(&mut buf[..count]);
+
+        Ok(count)
+    }
+}
+
+impl<S: Write, C: BlockCipher> Write for CtrStream<S, C> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut transformed = buf.to_vec();
+        self.apply_keystream(&mut transformed);
+
+        // Only the bytes the inner writer accepted consumed keystream
+        let count = self.inner.write(&transformed)?;
+        self.position -= (buf.len() - count) as u64;
+
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<S: Seek, C: BlockCipher> Seek for CtrStream<S, C> {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        self.position = self.inner.seek(position)?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+struct TrickleReader<R> {
+    inner: R,
+    max_read: usize,
+}
+
+#[cfg(test)]
+impl<R: Read> Read for TrickleReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let limit = buf.len().min(self.max_read);
+        self.inner.read(&mut buf[..limit])
+    }
+}
+
+// Fails every other write, without writing anything, until it's out of failures
+#[cfg(test)]
+struct FlakyWriter {
+    inner: Vec<u8>,
+    failures: usize,
+    fail_next: bool,
+}
+
+#[cfg(test)]
+impl Write for FlakyWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.fail_next = !self.fail_next;
+        if self.fail_next && self.failures > 0 {
+            self.failures -= 1;
+            return Err(io::Error::new(io::ErrorKind::Other, "try again"));
+        }
+
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn random_bytes(len: usize) -> Vec<u8> {
+    use rand::prelude::*;
+
+    let mut bytes = vec![0; len];
+    thread_rng().fill(&mut bytes[..]);
+    bytes
+}
+
+#[test]
+fn test_cbc_stream_matches_one_shot() {
+    use crate::aes::{aes_128_cbc_encrypt, Aes128};
+    use std::io::Cursor;
+
+    let key = b"YELLOW SUBMARINE";
+    let iv = [3; 16];
+
+    for &len in &[0, 1, 15, 16, 17, 4095, 4096, 4097, 100_003] {
+        let plaintext = random_bytes(len);
+        let expected_ciphertext = aes_128_cbc_encrypt(&plaintext, key, &iv);
+
+        let mut encryptor = CbcEncryptor::new(Vec::new(), Aes128::new(key), &iv);
+        for chunk in plaintext.chunks(13) {
+            encryptor.write_all(chunk).unwrap();
+        }
+        let ciphertext = encryptor.finish().unwrap();
+        assert_eq!(ciphertext, expected_ciphertext);
+
+        let reader = TrickleReader {
+            inner: Cursor::new(ciphertext),
+            max_read: 1000,
+        };
+        let mut decryptor = CbcDecryptor::new(reader, Aes128::new(key), &iv);
+
+        let mut decrypted = Vec::new();
+        let mut buffer = [0; 7];
+        loop {
+            match decryptor.read(&mut buffer).unwrap() {
+                0 => break,
+                count => decrypted.extend_from_slice(&buffer[..count]),
+            }
+        }
+
+        assert_eq!(decrypted, plaintext);
+    }
+}
+
+#[test]
+fn test_cbc_encryptor_survives_failed_writes() {
+    use crate::aes::{aes_128_cbc_encrypt, Aes128};
+
+    let key = b"YELLOW SUBMARINE";
+    let iv = [5; 16];
+    let plaintext = random_bytes(1000);
+
+    let writer = FlakyWriter {
+        inner: Vec::new(),
+        failures: 10,
+        fail_next: false,
+    };
+    let mut encryptor = CbcEncryptor::new(writer, Aes128::new(key), &iv);
+
+    // Retrying a chunk after an error has to give the same ciphertext as writing it once
+    let mut errors = 0;
+    for chunk in plaintext.chunks(37) {
+        while encryptor.write(chunk).is_err() {
+            errors += 1;
+        }
+    }
+    assert_eq!(errors, 10);
+
+    let writer = encryptor.finish().unwrap();
+    assert_eq!(writer.inner, aes_128_cbc_encrypt(&plaintext, key, &iv));
+}
+
+#[test]
+fn test_cbc_decryptor_rejects_bad_input() {
+    use crate::aes::{aes_128_cbc_encrypt, Aes128};
+    use std::io::Cursor;
+
+    let key = b"YELLOW SUBMARINE";
+    let iv = [0; 16];
+
+    let mut truncated = aes_128_cbc_encrypt(b"some plaintext", key, &iv);
+    truncated.pop();
+    let mut decryptor = CbcDecryptor::new(Cursor::new(truncated), Aes128::new(key), &iv);
+    let error = decryptor.read_to_end(&mut Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+    // A last plaintext byte of zero is never valid PKCS#7 padding
+    let unpadded = Cbc::new(Aes128::new(key)).encrypt_blocks(&[0; 32], &iv);
+    let mut decryptor = CbcDecryptor::new(Cursor::new(unpadded), Aes128::new(key), &iv);
+    let error = decryptor.read_to_end(&mut Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_ctr_stream_matches_one_shot() {
+    use crate::aes::{aes_128_ctr_encrypt, Aes128};
+    use std::io::Cursor;
+
+    let key = b"YELLOW SUBMARINE";
+    let nonce = [9; 8];
+    let plaintext = random_bytes(70_001);
+    let expected_ciphertext = aes_128_ctr_encrypt(&plaintext, key, &nonce);
+
+    let mut writer = CtrStream::new(Vec::new(), Aes128::new(key), &nonce);
+    for chunk in plaintext.chunks(11) {
+        writer.write_all(chunk).unwrap();
+    }
+    assert_eq!(writer.into_inner(), expected_ciphertext);
+
+    let reader = TrickleReader {
+        inner: Cursor::new(expected_ciphertext),
+        max_read: 5,
+    };
+    let mut decrypted = Vec::new();
+    CtrStream::new(reader, Aes128::new(key), &nonce)
+        .read_to_end(&mut decrypted)
+        .unwrap();
+    assert_eq!(decrypted, plaintext);
+}
+
+#[test]
+fn test_ctr_stream_seek() {
+    use crate::aes::{aes_128_ctr_encrypt, Aes128};
+    use std::io::Cursor;
+
+    let key = b"YELLOW SUBMARINE";
+    let nonce = [4; 8];
+    let plaintext = random_bytes(10_000);
+    let ciphertext = aes_128_ctr_encrypt(&plaintext, key, &nonce);
+
+    let mut stream = CtrStream::new(Cursor::new(ciphertext), Aes128::new(key), &nonce);
+    let mut buffer = [0; 100];
+
+    stream.seek(SeekFrom::Start(5_003)).unwrap();
+    stream.read_exact(&mut buffer).unwrap();
+    assert_eq!(&buffer[..], &plaintext[5_003..5_103]);
+
+    stream.seek(SeekFrom::Current(-1_000)).unwrap();
+    stream.read_exact(&mut buffer).unwrap();
+    assert_eq!(&buffer[..], &plaintext[4_103..4_203]);
+}
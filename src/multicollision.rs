@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use rand::{thread_rng, Rng};
+
+use crate::hasher::{Hasher, MerkleDamgard};
+
+// Two single blocks that take the same chaining value to the same next one
+#[derive(Clone, Debug)]
+pub struct BlockCollision {
+    pub blocks: [Vec<u8>; 2],
+    pub registers: Vec<u8>,
+}
+
+// Birthday search over random blocks: for an n-bit chaining value, expect around 2^(n/2)
+// compressions. Only practical for hashes with a tiny state
+pub fn find_block_collision<H: MerkleDamgard>(registers: &[u8]) -> BlockCollision {
+    let mut rng = thread_rng();
+    let mut seen = HashMap::new();
+
+    loop {
+        let mut block = vec![0; H::BLOCK_SIZE];
+        rng.fill(&mut block[..]);
+
+        let next_registers = H::compress(registers, &block);
+
+        match seen.insert(next_registers.clone(), block.clone()) {
+            Some(other) if other != block => {
+                return BlockCollision {
+                    blocks: [other, block],
+                    registers: next_registers,
+                };
+            }
+            _ => {}
+        }
+    }
+}
+
+// Same, but starting from two different chaining values: `blocks[0]` takes `left_registers` and
+// `blocks[1]` takes `right_registers` to the same place
+pub fn find_block_collision_between<H: MerkleDamgard>(
+    left_registers: &[u8],
+    right_registers: &[u8],
+) -> BlockCollision {
+    assert_ne!(left_registers, right_registers);
+
+    let mut rng = thread_rng();
+    let mut left_seen = HashMap::new();
+    let mut right_seen = HashMap::new();
+
+    loop {
+        let mut block = vec![0; H::BLOCK_SIZE];
+        rng.fill(&mut block[..]);
+
+        let left = H::compress(left_registers, &block);
+        let right = H::compress(right_registers, &block);
+
+        if let Some(other) = right_seen.remove(&left) {
+            return BlockCollision {
+                blocks: [block, other],
+                registers: left,
+            };
+        }
+
+        if let Some(other) = left_seen.remove(&right) {
+            return BlockCollision {
+                blocks: [other, block],
+                registers: right,
+            };
+        }
+
+        left_seen.insert(left, block.clone());
+        right_seen.insert(right, block);
+    }
+}
+
+// Joux's multicollision: chaining n block collisions, each one starting from where the previous
+// one ended, gives 2^n messages of n blocks that all hash the same, for n times the cost of a
+// single collision. Message `i` takes the second block of collision `j` if bit `j` of `i` is set
+pub struct Multicollision<H> {
+    initial_registers: Vec<u8>,
+    collisions: Vec<BlockCollision>,
+    hasher: PhantomData<H>,
+}
+
+impl<H: MerkleDamgard> Multicollision<H> {
+    pub fn new(collision_count: usize) -> Self {
+        Self::from_registers(&H::new().registers(), collision_count)
+    }
+
+    pub fn from_registers(registers: &[u8], collision_count: usize) -> Self {
+        let mut multicollision = Self {
+            initial_registers: registers.to_vec(),
+            collisions: Vec::with_capacity(collision_count),
+            hasher: PhantomData,
+        };
+
+        multicollision.extend(collision_count);
+        multicollision
+    }
+
+    // Doubles the number of messages for each extra collision
+    pub fn extend(&mut self, collision_count: usize) {
+        for _ in 0..collision_count {
+            let collision = find_block_collision::<H>(self.registers());
+            self.collisions.push(collision);
+        }
+    }
+
+    // The chaining value every message ends on
+    pub fn registers(&self) -> &[u8] {
+        self.collisions
+            .last()
+            .map_or(&self.initial_registers, |collision| &collision.registers)
+    }
+
+    pub fn collisions(&self) -> &[BlockCollision] {
+        &self.collisions
+    }
+
+    pub fn message_len(&self) -> usize {
+        self.collisions.len() * H::BLOCK_SIZE
+    }
+
+    pub fn message_count(&self) -> usize {
+        1 << self.collisions.len()
+    }
+
+    pub fn message(&self, index: usize) -> Vec<u8> {
+        assert!(index < self.message_count());
+
+        let mut message = Vec::with_capacity(self.message_len());
+        for (bit, collision) in self.collisions.iter().enumerate() {
+            message.extend_from_slice(&collision.blocks[(index >> bit) & 1]);
+        }
+
+        message
+    }
+
+    pub fn messages(&self) -> impl Iterator<Item = Vec<u8>> + '_ {
+        (0..self.message_count()).map(move |index| self.message(index))
+    }
+}
+
+// A collision in the cascade `F(m) || G(m)`, found by building a multicollision in the cheap `F`
+// that's big enough for a birthday collision in `G` to be likely among its messages. Besides the
+// messages, returns how many block collisions in `F` it took, which is about half of `G`'s bits:
+// the whole thing costs little more than attacking `G` on its own
+pub fn find_cascade_collision<F: MerkleDamgard, G: Hasher>() -> (Vec<u8>, Vec<u8>, usize) {
+    let mut multicollision = Multicollision::<F>::new(G::OUTPUT_SIZE * 8 / 2);
+
+    loop {
+        let mut seen = HashMap::with_capacity(multicollision.message_count());
+
+        for message in multicollision.messages() {
+            if let Some(other) = seen.insert(G::digest(&message), message.clone()) {
+                return (other, message, multicollision.collisions().len());
+            }
+        }
+
+        // Unlucky: no collision in `G` among these, so try again with twice as many
+        multicollision.extend(1);
+    }
+}
+
+#[cfg(test)]
+use crate::toy_hash::{ToyHash16, ToyHash24};
+
+#[test]
+fn test_joux_multicollision() {
+    let multicollision = Multicollision::<ToyHash16>::new(4);
+    assert_eq!(multicollision.message_count(), 16);
+
+    let mut messages: Vec<_> = multicollision.messages().collect();
+    let digest = ToyHash16::digest(&messages[0]);
+
+    for message in messages.iter() {
+        assert_eq!(message.len(), 4 * ToyHash16::BLOCK_SIZE);
+        assert_eq!(ToyHash16::digest(message), digest);
+    }
+
+    messages.sort();
+    messages.dedup();
+    assert_eq!(messages.len(), 16);
+}
+
+#[test]
+fn test_cascade_collision() {
+    let (left, right, collision_count) = find_cascade_collision::<ToyHash16, ToyHash24>();
+
+    assert_ne!(left, right);
+    assert!(collision_count >= 12);
+    assert_eq!(ToyHash16::digest(&left), ToyHash16::digest(&right));
+    assert_eq!(ToyHash24::digest(&left), ToyHash24::digest(&right));
+}
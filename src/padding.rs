@@ -1,6 +1,10 @@
 use std::iter;
 
+use crate::error::{or_panic, Error, Result};
+
 pub fn pkcs7_pad(bytes: &[u8], block_size: usize) -> Vec<u8> {
+    or_panic(check_block_size(block_size, MAX_LENGTH_BYTE_BLOCK_SIZE));
+
     let mut result = bytes.to_vec();
     let mut pad_size = block_size - (bytes.len() % block_size);
 
@@ -13,17 +17,21 @@ pub fn pkcs7_pad(bytes: &[u8], block_size: usize) -> Vec<u8> {
     result
 }
 
-pub fn pkcs7_unpad(bytes: &[u8]) -> Result<Vec<u8>, &'static str> {
+// Returns as soon as it finds a byte that isn't padding, so how long a bad padding takes to
+// reject says where it went wrong. Use `pkcs7_unpad_constant_time` where that matters
+pub fn pkcs7_unpad(bytes: &[u8], block_size: usize) -> Result<Vec<u8>> {
+    check_padded_len(bytes, block_size, MAX_LENGTH_BYTE_BLOCK_SIZE)?;
+
     let pad_byte = bytes[bytes.len() - 1];
     let pad_size = pad_byte as usize;
 
-    if pad_size == 0 || pad_size > bytes.len() {
-        return Err("Bad Padding");
+    if pad_size == 0 || pad_size > block_size {
+        return Err(Error::BadPadding);
     }
 
     for &byte in bytes.iter().rev().take(pad_size) {
         if byte != pad_byte {
-            return Err("Bad Padding");
+            return Err(Error::BadPadding);
         }
     }
 
@@ -31,41 +39,311 @@ pub fn pkcs7_unpad(bytes: &[u8]) -> Result<Vec<u8>, &'static str> {
     Ok(result)
 }
 
+// Looks at every byte of the last block whatever the padding turns out to be, and only branches
+// once at the end. The lengths checked up front are public anyway
+pub fn pkcs7_unpad_constant_time(bytes: &[u8], block_size: usize) -> Result<Vec<u8>> {
+    check_padded_len(bytes, block_size, MAX_LENGTH_BYTE_BLOCK_SIZE)?;
+
+    let last_block = &bytes[bytes.len() - block_size..];
+    let pad_byte = last_block[block_size - 1];
+    let pad_size = pad_byte as u32;
+
+    // Stays zero only if the pad size is in 1..=block_size and every padding byte matches it
+    let mut bad = is_less_than_mask(pad_size, 1) | is_less_than_mask(block_size as u32, pad_size);
+
+    for (index, &byte) in last_block.iter().rev().enumerate() {
+        let in_padding = is_less_than_mask(index as u32, pad_size);
+        bad |= in_padding & (byte ^ pad_byte);
+    }
+
+    if bad != 0 {
+        return Err(Error::BadPadding);
+    }
+
+    Ok(bytes[..bytes.len() - pad_byte as usize].to_vec())
+}
+
+// 0xff if a < b, 0 otherwise, without branching. Both have to fit in 31 bits
+fn is_less_than_mask(a: u32, b: u32) -> u8 {
+    let borrow = a.wrapping_sub(b) >> 31;
+    (borrow as u8).wrapping_neg()
+}
+
+fn check_padded_len(bytes: &[u8], block_size: usize, max_block_size: usize) -> Result<()> {
+    check_block_size(block_size, max_block_size)?;
+
+    if bytes.is_empty() {
+        return Err(Error::InvalidLength {
+            expected: block_size,
+            actual: 0,
+        });
+    }
+
+    if !bytes.len().is_multiple_of(block_size) {
+        return Err(Error::NotBlockAligned {
+            block_size,
+            length: bytes.len(),
+        });
+    }
+
+    Ok(())
+}
+
+// Block cipher padding schemes, so modes can be used with something other than PKCS#7. `unpad`
+// also gets the block size, since no scheme ever adds more than a block
+pub trait Padding {
+    fn pad(&self, bytes: &[u8], block_size: usize) -> Vec<u8>;
+    fn unpad(&self, bytes: &[u8], block_size: usize) -> Result<Vec<u8>>;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Pkcs7;
+
+impl Padding for Pkcs7 {
+    fn pad(&self, bytes: &[u8], block_size: usize) -> Vec<u8> {
+        pkcs7_pad(bytes, block_size)
+    }
+
+    fn unpad(&self, bytes: &[u8], block_size: usize) -> Result<Vec<u8>> {
+        pkcs7_unpad_constant_time(bytes, block_size)
+    }
+}
+
+// Zeros, then the padding length in the last byte
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AnsiX923;
+
+impl Padding for AnsiX923 {
+    fn pad(&self, bytes: &[u8], block_size: usize) -> Vec<u8> {
+        or_panic(check_block_size(block_size, MAX_LENGTH_BYTE_BLOCK_SIZE));
+        let pad_size = pad_size(bytes, block_size);
+
+        let mut result = bytes.to_vec();
+        result.resize(bytes.len() + pad_size - 1, 0);
+        result.push(pad_size as u8);
+        result
+    }
+
+    fn unpad(&self, bytes: &[u8], block_size: usize) -> Result<Vec<u8>> {
+        let pad_size = trailing_pad_size(bytes, block_size)?;
+        let unpadded_len = bytes.len() - pad_size;
+
+        if bytes[unpadded_len..bytes.len() - 1]
+            .iter()
+            .any(|&byte| byte != 0)
+        {
+            return Err(Error::BadPadding);
+        }
+
+        Ok(bytes[..unpadded_len].to_vec())
+    }
+}
+
+// Random bytes, then the padding length in the last byte. Only the length can be checked
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Iso10126;
+
+impl Padding for Iso10126 {
+    fn pad(&self, bytes: &[u8], block_size: usize) -> Vec<u8> {
+        use rand::prelude::*;
+
+        or_panic(check_block_size(block_size, MAX_LENGTH_BYTE_BLOCK_SIZE));
+        let pad_size = pad_size(bytes, block_size);
+        let mut rng = thread_rng();
+
+        let mut result = bytes.to_vec();
+        result.extend((1..pad_size).map(|_| rng.gen::<u8>()));
+        result.push(pad_size as u8);
+        result
+    }
+
+    fn unpad(&self, bytes: &[u8], block_size: usize) -> Result<Vec<u8>> {
+        let pad_size = trailing_pad_size(bytes, block_size)?;
+        Ok(bytes[..bytes.len() - pad_size].to_vec())
+    }
+}
+
+// A single 0x80 byte (a one bit), then zeros. Also known as bit padding
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Iso7816;
+
+impl Padding for Iso7816 {
+    fn pad(&self, bytes: &[u8], block_size: usize) -> Vec<u8> {
+        or_panic(check_block_size(block_size, usize::MAX));
+        let pad_size = pad_size(bytes, block_size);
+
+        let mut result = bytes.to_vec();
+        result.push(0x80);
+        result.resize(bytes.len() + pad_size, 0);
+        result
+    }
+
+    fn unpad(&self, bytes: &[u8], block_size: usize) -> Result<Vec<u8>> {
+        check_padded_len(bytes, block_size, usize::MAX)?;
+
+        match bytes.iter().rposition(|&byte| byte != 0) {
+            Some(marker) if bytes[marker] == 0x80 && bytes.len() - marker <= block_size => {
+                Ok(bytes[..marker].to_vec())
+            }
+
+            _ => Err(Error::BadPadding),
+        }
+    }
+}
+
+// Zeros up to the end of the block, and nothing at all if the data is already aligned. Unpadding
+// can't tell padding from trailing zeros in the data, and never fails on whole blocks, so there's
+// nothing for a padding oracle to leak either
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ZeroPadding;
+
+impl Padding for ZeroPadding {
+    fn pad(&self, bytes: &[u8], block_size: usize) -> Vec<u8> {
+        or_panic(check_block_size(block_size, usize::MAX));
+
+        let mut result = bytes.to_vec();
+        result.resize(bytes.len().div_ceil(block_size) * block_size, 0);
+        result
+    }
+
+    fn unpad(&self, bytes: &[u8], block_size: usize) -> Result<Vec<u8>> {
+        check_padded_len(bytes, block_size, usize::MAX)?;
+
+        let zeros = bytes
+            .iter()
+            .rev()
+            .take(block_size - 1)
+            .take_while(|&&byte| byte == 0)
+            .count();
+
+        Ok(bytes[..bytes.len() - zeros].to_vec())
+    }
+}
+
+// Schemes that write the padding length into a byte can't go past a 255-byte block
+const MAX_LENGTH_BYTE_BLOCK_SIZE: usize = 255;
+
+fn check_block_size(block_size: usize, max_block_size: usize) -> Result<()> {
+    if block_size == 0 {
+        return Err(Error::InvalidLength {
+            expected: 1,
+            actual: 0,
+        });
+    }
+
+    if block_size > max_block_size {
+        return Err(Error::InvalidLength {
+            expected: max_block_size,
+            actual: block_size,
+        });
+    }
+
+    Ok(())
+}
+
+// Padding always adds between 1 and `block_size` bytes
+fn pad_size(bytes: &[u8], block_size: usize) -> usize {
+    block_size - bytes.len() % block_size
+}
+
+// For schemes that store the padding length on the last byte
+fn trailing_pad_size(bytes: &[u8], block_size: usize) -> Result<usize> {
+    check_padded_len(bytes, block_size, MAX_LENGTH_BYTE_BLOCK_SIZE)?;
+
+    let pad_size = *bytes.last().ok_or(Error::BadPadding)? as usize;
+
+    if pad_size == 0 || pad_size > block_size || pad_size > bytes.len() {
+        return Err(Error::BadPadding);
+    }
+
+    Ok(pad_size)
+}
+
+// How Merkle–Damgård padding ends: the message length in bits, as a fixed-size integer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LengthEncoding {
+    BigEndian64,
+    LittleEndian64,
+    BigEndian128,
+}
+
+impl LengthEncoding {
+    fn size(self) -> usize {
+        match self {
+            LengthEncoding::BigEndian64 | LengthEncoding::LittleEndian64 => 8,
+            LengthEncoding::BigEndian128 => 16,
+        }
+    }
+
+    fn encode(self, bits: u128) -> Vec<u8> {
+        match self {
+            LengthEncoding::BigEndian64 => (bits as u64).to_be_bytes().to_vec(),
+            LengthEncoding::LittleEndian64 => (bits as u64).to_le_bytes().to_vec(),
+            LengthEncoding::BigEndian128 => bits.to_be_bytes().to_vec(),
+        }
+    }
+}
+
+// MD4, SHA-1 and SHA-256 style: 512-bit blocks and a 64-bit length
 pub fn md_padding(message: &[u8], previous_bits: u64) -> Vec<u8> {
-    md_padding_inner(message, previous_bits, u64::to_be_bytes)
+    md_padding_with(
+        message,
+        previous_bits as u128,
+        64,
+        LengthEncoding::BigEndian64,
+    )
 }
 
 pub fn md_padding_le_count(message: &[u8], previous_bits: u64) -> Vec<u8> {
-    md_padding_inner(message, previous_bits, u64::to_le_bytes)
-}
-
-fn md_padding_inner<F>(message: &[u8], previous_bits: u64, count_transform: F) -> Vec<u8>
-where
-    F: Fn(u64) -> [u8; 8],
-{
-    let current_mod = (message.len() * 8 + 1) % 512;
-    let zeroes_to_append = if current_mod < 448 {
-        448 - current_mod
-    } else {
-        (512 - current_mod) + 448
-    };
+    md_padding_with(
+        message,
+        previous_bits as u128,
+        64,
+        LengthEncoding::LittleEndian64,
+    )
+}
+
+// A one bit, then zeros up to where the length fits right at the end of a block. `previous_bits`
+// counts whatever was hashed before `message`
+pub fn md_padding_with(
+    message: &[u8],
+    previous_bits: u128,
+    block_size: usize,
+    length: LengthEncoding,
+) -> Vec<u8> {
+    let total_len_bits = message.len() as u128 * 8 + previous_bits;
+
+    let unpadded_len = message.len() + 1 + length.size();
+    let padded_len = unpadded_len.div_ceil(block_size) * block_size;
 
-    let bytes_to_append = (zeroes_to_append as usize + 1) / 8;
-    let mut result = Vec::with_capacity(message.len() + bytes_to_append + 4);
+    let mut result = Vec::with_capacity(padded_len);
     result.extend_from_slice(message);
+    result.push(1 << 7);
+    result.resize(padded_len - length.size(), 0);
+    result.extend(length.encode(total_len_bits));
 
-    let first_byte = 1 << 7;
-    result.push(first_byte);
+    result
+}
 
-    for _ in 1..bytes_to_append {
-        result.push(0);
-    }
+// Only the padding `md_padding` appends to a message of that length, without the message itself
+pub fn md_padding_for_len(message_len: usize) -> Vec<u8> {
+    md_padding_for_len_with(message_len, 64, LengthEncoding::BigEndian64)
+}
 
-    // add 64-bit length
-    let total_len_bits = (message.len() * 8) as u64 + previous_bits;
-    result.extend_from_slice(&count_transform(total_len_bits));
+pub fn md_padding_le_count_for_len(message_len: usize) -> Vec<u8> {
+    md_padding_for_len_with(message_len, 64, LengthEncoding::LittleEndian64)
+}
 
-    result
+pub fn md_padding_for_len_with(
+    message_len: usize,
+    block_size: usize,
+    length: LengthEncoding,
+) -> Vec<u8> {
+    let tail_len = message_len % block_size;
+    let previous_bits = (message_len - tail_len) as u128 * 8;
+
+    let mut padding = md_padding_with(&vec![0; tail_len], previous_bits, block_size, length);
+    padding.split_off(tail_len)
 }
 
 #[test]
@@ -75,4 +353,198 @@ fn test_md_padding() {
     assert_eq!((result.len() * 8) % 512, 0);
     assert_eq!(result[3], 1 << 7);
     assert_eq!(result[result.len() - 1], 24);
+
+    let result = md_padding_with(&[0; 111], 0, 128, LengthEncoding::BigEndian128);
+    assert_eq!(result.len(), 128);
+    assert_eq!(result[111], 1 << 7);
+    assert_eq!(&result[112..126], &[0; 14]);
+    assert_eq!(&result[126..], &[3, 120]);
+
+    // One more byte and the length no longer fits in the same block
+    let result = md_padding_with(&[0; 112], 1 << 64, 128, LengthEncoding::BigEndian128);
+    assert_eq!(result.len(), 256);
+    assert_eq!(result[112], 1 << 7);
+    assert_eq!(&result[240..], &(896 + (1_u128 << 64)).to_be_bytes());
+}
+
+#[test]
+fn test_pkcs7_unpad_rejects_bad_padding() {
+    for unpad in [pkcs7_unpad, pkcs7_unpad_constant_time].iter() {
+        assert_eq!(
+            unpad(b"ICE ICE BABY\x04\x04\x04\x04", 16),
+            Ok(b"ICE ICE BABY".to_vec())
+        );
+        assert_eq!(
+            unpad(b"ICE ICE BABY\x01\x02\x03\x04", 16),
+            Err(Error::BadPadding)
+        );
+        assert_eq!(
+            unpad(b"ICE ICE BABY\x05\x05\x05\x05", 16),
+            Err(Error::BadPadding)
+        );
+        assert_eq!(
+            unpad(b"ICE ICE BABY\x04\x04\x04\x00", 16),
+            Err(Error::BadPadding)
+        );
+        assert_eq!(
+            unpad(b"YELLOW SUBMARINE\x11\x11\x11\x11", 4),
+            Err(Error::BadPadding)
+        );
+        assert_eq!(unpad(&[16; 32], 16), Ok(vec![16; 16]));
+        assert_eq!(unpad(&[17; 32], 16), Err(Error::BadPadding));
+
+        assert_eq!(
+            unpad(b"ICE ICE BABY\x03\x03\x03", 16),
+            Err(Error::NotBlockAligned {
+                block_size: 16,
+                length: 15
+            })
+        );
+        assert_eq!(
+            unpad(b"", 16),
+            Err(Error::InvalidLength {
+                expected: 16,
+                actual: 0
+            })
+        );
+    }
+}
+
+// Compares how long each variant takes to reject a padding that's wrong right at the start of the
+// scan with one that's only wrong at the very end. Timings are too noisy to run with everything
+// else, so use `cargo test --release -- --ignored test_pkcs7_unpad_timing`
+#[test]
+#[ignore]
+fn test_pkcs7_unpad_timing() {
+    use std::hint::black_box;
+    use std::time::{Duration, Instant};
+
+    const BLOCK_SIZE: usize = 255;
+    const ROUNDS: usize = 200_000;
+
+    fn time(unpad: fn(&[u8], usize) -> Result<Vec<u8>>, bytes: &[u8]) -> Duration {
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            assert!(black_box(unpad(black_box(bytes), BLOCK_SIZE)).is_err());
+        }
+
+        start.elapsed()
+    }
+
+    let mut wrong_early = vec![0xff; BLOCK_SIZE];
+    wrong_early[BLOCK_SIZE - 2] = 0;
+    let mut wrong_late = vec![0xff; BLOCK_SIZE];
+    wrong_late[0] = 0;
+
+    let ratio = |unpad| {
+        let early = time(unpad, &wrong_early);
+        let late = time(unpad, &wrong_late);
+
+        late.as_secs_f64() / early.as_secs_f64()
+    };
+
+    let early_exit_ratio = ratio(pkcs7_unpad);
+    let constant_time_ratio = ratio(pkcs7_unpad_constant_time);
+
+    assert!(
+        early_exit_ratio > 2.0,
+        "Early exit variant only took {:.2}x as long on late errors",
+        early_exit_ratio
+    );
+    assert!(
+        constant_time_ratio < 1.5 && constant_time_ratio > 1.0 / 1.5,
+        "Constant time variant took {:.2}x as long on late errors",
+        constant_time_ratio
+    );
+}
+
+#[test]
+fn test_padding_schemes() {
+    let schemes: [(&dyn Padding, &[u8]); 4] = [
+        (&Pkcs7, b"YELLOW SUBMARINE\x04\x04\x04\x04"),
+        (&AnsiX923, b"YELLOW SUBMARINE\x00\x00\x00\x04"),
+        (&Iso7816, b"YELLOW SUBMARINE\x80\x00\x00\x00"),
+        (&ZeroPadding, b"YELLOW SUBMARINE\x00\x00\x00\x00"),
+    ];
+
+    for &(scheme, padded) in schemes.iter() {
+        assert_eq!(scheme.pad(b"YELLOW SUBMARINE", 20), padded);
+        assert_eq!(scheme.unpad(padded, 20), Ok(b"YELLOW SUBMARINE".to_vec()));
+    }
+
+    let padded = Iso10126.pad(b"YELLOW SUBMARINE", 20);
+    assert_eq!(padded.len(), 20);
+    assert_eq!(padded[19], 4);
+    assert_eq!(
+        Iso10126.unpad(&padded, 20),
+        Ok(b"YELLOW SUBMARINE".to_vec())
+    );
+
+    // Aligned data gets a whole block of padding, except with zero padding
+    assert_eq!(
+        AnsiX923.pad(b"YELLOW", 6),
+        b"YELLOW\x00\x00\x00\x00\x00\x06"
+    );
+    assert_eq!(Iso7816.pad(b"YELLOW", 6), b"YELLOW\x80\x00\x00\x00\x00\x00");
+    assert_eq!(ZeroPadding.pad(b"YELLOW", 6), b"YELLOW");
+}
+
+#[test]
+fn test_padding_schemes_reject_bad_padding() {
+    assert_eq!(
+        AnsiX923.unpad(b"YELLOW SUBMARINE\x00\x01\x00\x04", 20),
+        Err(Error::BadPadding)
+    );
+    assert_eq!(AnsiX923.unpad(b"YELLOW\x00\x07", 4), Err(Error::BadPadding));
+    assert_eq!(Iso10126.unpad(b"YELLOW\x00\x00", 4), Err(Error::BadPadding));
+    assert_eq!(
+        Iso7816.unpad(b"YELLOW SUBMARINE\x80\x00\x01\x00", 20),
+        Err(Error::BadPadding)
+    );
+    assert_eq!(
+        Iso7816.unpad(b"\x80\x00\x00\x00\x00\x00\x00\x00", 4),
+        Err(Error::BadPadding)
+    );
+    assert_eq!(
+        Iso7816.unpad(b"\x00\x00\x00\x00", 4),
+        Err(Error::BadPadding)
+    );
+
+    // Every scheme only ever produces whole blocks
+    let schemes: [&dyn Padding; 4] = [&AnsiX923, &Iso10126, &Iso7816, &ZeroPadding];
+    for scheme in schemes.iter() {
+        assert_eq!(
+            scheme.unpad(b"YELLO\x03", 4),
+            Err(Error::NotBlockAligned {
+                block_size: 4,
+                length: 6
+            })
+        );
+        assert_eq!(
+            scheme.unpad(b"", 4),
+            Err(Error::InvalidLength {
+                expected: 4,
+                actual: 0
+            })
+        );
+    }
+
+    let zero_block_size = Err(Error::InvalidLength {
+        expected: 1,
+        actual: 0,
+    });
+    assert_eq!(pkcs7_unpad(b"YELLOW", 0), zero_block_size);
+    assert_eq!(AnsiX923.unpad(b"YELLOW", 0), zero_block_size);
+    assert_eq!(Iso7816.unpad(b"YELLOW", 0), zero_block_size);
+    assert_eq!(ZeroPadding.unpad(b"YELLOW", 0), zero_block_size);
+
+    // The length byte can't say 256
+    let too_big = Err(Error::InvalidLength {
+        expected: 255,
+        actual: 256,
+    });
+    assert_eq!(pkcs7_unpad_constant_time(&[1; 256], 256), too_big);
+    assert_eq!(AnsiX923.unpad(&[1; 256], 256), too_big);
+    assert_eq!(Iso10126.unpad(&[1; 256], 256), too_big);
+    assert_eq!(Iso7816.pad(b"YELLOW", 256).len(), 256);
 }
@@ -1,6 +1,7 @@
 use rug::Integer;
 
-use crate::bignum::{self, invmod, lcm};
+use crate::bignum::{self, lcm, try_invmod};
+use crate::error::Result;
 
 #[derive(Debug)]
 pub struct PublicKey {
@@ -15,8 +16,13 @@ pub struct PrivateKey {
 }
 
 pub fn keygen(keysize: u32, public_exponent: Integer) -> (PublicKey, PrivateKey) {
-    let mut p = bignum::random_prime(keysize / 2);
-    let mut q = bignum::random_prime(keysize / 2);
+    try_keygen(keysize, public_exponent).unwrap_or_else(|err| panic!("RSA keygen failed: {}", err))
+}
+
+// Fails with `Error::NotInvertible` if the public exponent isn't coprime with λ(n)
+pub fn try_keygen(keysize: u32, public_exponent: Integer) -> Result<(PublicKey, PrivateKey)> {
+    let mut p = bignum::try_random_prime(keysize / 2)?;
+    let mut q = bignum::try_random_prime(keysize / 2)?;
 
     let et = {
         p -= 1;
@@ -31,7 +37,7 @@ pub fn keygen(keysize: u32, public_exponent: Integer) -> (PublicKey, PrivateKey)
 
     let n = p * q;
 
-    let d = invmod(&public_exponent, &et);
+    let d = try_invmod(&public_exponent, &et)?;
 
     let public_key = PublicKey {
         modulus: n.clone(),
@@ -42,7 +48,7 @@ pub fn keygen(keysize: u32, public_exponent: Integer) -> (PublicKey, PrivateKey)
         exponent: d,
     };
 
-    (public_key, private_key)
+    Ok((public_key, private_key))
 }
 
 pub fn encrypt(public_key: &PublicKey, message: &[u8]) -> Vec<u8> {
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use rand::{thread_rng, Rng};
+
+use crate::error::{or_panic, Error, Result};
+use crate::hasher::MerkleDamgard;
+use crate::multicollision::{self, BlockCollision};
+
+// Kelsey and Schneier's expandable message: k collisions, each between a single block and a
+// message of 2^i + 1 blocks, for i going down from k - 1 to 0. Picking one side of every
+// collision gives messages that all end on the same chaining value, with any length from k to
+// k + 2^k - 1 blocks
+pub struct ExpandableMessage<H> {
+    collisions: Vec<(Vec<u8>, Vec<u8>)>,
+    registers: Vec<u8>,
+    hasher: PhantomData<H>,
+}
+
+impl<H: MerkleDamgard> ExpandableMessage<H> {
+    pub fn new(k: usize) -> Self {
+        Self::from_registers(&H::new().registers(), k)
+    }
+
+    pub fn from_registers(registers: &[u8], k: usize) -> Self {
+        let mut registers = registers.to_vec();
+        let mut collisions = Vec::with_capacity(k);
+        let mut rng = thread_rng();
+
+        for i in (0..k).rev() {
+            // The long side runs through 2^i throwaway blocks before its colliding one. If they
+            // happen to lead right back to where they started there's nothing to collide, so
+            // they're random and get redrawn until they don't
+            let (mut long, dummy_registers) = loop {
+                let mut long = vec![0; (1 << i) * H::BLOCK_SIZE];
+                rng.fill(&mut long[..]);
+
+                let dummy_registers = long
+                    .chunks(H::BLOCK_SIZE)
+                    .fold(registers.clone(), |registers, block| {
+                        H::compress(&registers, block)
+                    });
+
+                if dummy_registers != registers {
+                    break (long, dummy_registers);
+                }
+            };
+
+            let BlockCollision {
+                blocks: [short, last_block],
+                registers: next_registers,
+            } = multicollision::find_block_collision_between::<H>(&registers, &dummy_registers);
+
+            long.extend_from_slice(&last_block);
+            collisions.push((short, long));
+            registers = next_registers;
+        }
+
+        Self {
+            collisions,
+            registers,
+            hasher: PhantomData,
+        }
+    }
+
+    pub fn min_blocks(&self) -> usize {
+        self.collisions.len()
+    }
+
+    pub fn max_blocks(&self) -> usize {
+        self.min_blocks() + (1 << self.collisions.len()) - 1
+    }
+
+    // The chaining value every message ends on
+    pub fn registers(&self) -> &[u8] {
+        &self.registers
+    }
+
+    // Every extra block over the minimum is a bit of `block_count - k`, and the collision for
+    // bit i is the one whose long side is 2^i blocks longer
+    pub fn message(&self, block_count: usize) -> Vec<u8> {
+        or_panic(self.try_message(block_count))
+    }
+
+    pub fn try_message(&self, block_count: usize) -> Result<Vec<u8>> {
+        if block_count < self.min_blocks() || block_count > self.max_blocks() {
+            return Err(Error::InvalidLength {
+                expected: self.max_blocks(),
+                actual: block_count,
+            });
+        }
+
+        let extra_blocks = block_count - self.min_blocks();
+        let k = self.collisions.len();
+
+        let mut message = Vec::with_capacity(block_count * H::BLOCK_SIZE);
+        for (index, (short, long)) in self.collisions.iter().enumerate() {
+            if extra_blocks & (1 << (k - 1 - index)) != 0 {
+                message.extend_from_slice(long);
+            } else {
+                message.extend_from_slice(short);
+            }
+        }
+
+        Ok(message)
+    }
+}
+
+// The chaining value after each full block of the message, without padding
+pub fn intermediate_states<H: MerkleDamgard>(message: &[u8]) -> Vec<Vec<u8>> {
+    message
+        .chunks_exact(H::BLOCK_SIZE)
+        .scan(H::new().registers(), |registers, block| {
+            *registers = H::compress(registers, block);
+            Some(registers.clone())
+        })
+        .collect()
+}
+
+pub fn find_second_preimage<H: MerkleDamgard>(target: &[u8]) -> Vec<u8> {
+    or_panic(try_find_second_preimage::<H>(target))
+}
+
+// Long-message second preimage: with a target of around 2^k blocks, a random block out of a
+// k-expandable message lands on one of the target's intermediate states after about 2^n / 2^k
+// tries, instead of 2^n. Whatever comes before that state can then be replaced by an expandable
+// message of the right length, so the forgery has the same length (and so the same padding) as
+// the target
+pub fn try_find_second_preimage<H: MerkleDamgard>(target: &[u8]) -> Result<Vec<u8>> {
+    let block_count = target.len() / H::BLOCK_SIZE;
+    if block_count < 2 {
+        return Err(Error::InvalidLength {
+            expected: 2 * H::BLOCK_SIZE,
+            actual: target.len(),
+        });
+    }
+
+    // The largest k with 2^k <= block_count
+    let k = (usize::BITS - 1 - block_count.leading_zeros()) as usize;
+    let expandable = ExpandableMessage::<H>::from_registers(&H::new().registers(), k);
+
+    // The bridge block comes right after the expandable message, so it can only reach states
+    // that come after a prefix the expandable message can stand in for
+    let states: HashMap<Vec<u8>, usize> = intermediate_states::<H>(target)
+        .into_iter()
+        .enumerate()
+        .map(|(index, registers)| (registers, index + 1))
+        .filter(|&(_, blocks)| {
+            blocks > expandable.min_blocks() && blocks <= expandable.max_blocks() + 1
+        })
+        .collect();
+
+    let mut rng = thread_rng();
+    let mut bridge = vec![0; H::BLOCK_SIZE];
+
+    loop {
+        rng.fill(&mut bridge[..]);
+
+        if let Some(&blocks) = states.get(&H::compress(expandable.registers(), &bridge)) {
+            let mut forged = expandable.message(blocks - 1);
+            forged.extend_from_slice(&bridge);
+            forged.extend_from_slice(&target[blocks * H::BLOCK_SIZE..]);
+
+            return Ok(forged);
+        }
+    }
+}
+
+#[cfg(test)]
+use crate::{
+    hasher::Hasher,
+    toy_hash::{ToyHash16, ToyHash24},
+};
+
+#[test]
+fn test_expandable_message() {
+    let expandable = ExpandableMessage::<ToyHash24>::new(4);
+    assert_eq!(expandable.min_blocks(), 4);
+    assert_eq!(expandable.max_blocks(), 19);
+
+    for block_count in 4..=19 {
+        let message = expandable.message(block_count);
+        assert_eq!(message.len(), block_count * ToyHash24::BLOCK_SIZE);
+        assert_eq!(
+            intermediate_states::<ToyHash24>(&message).last().unwrap(),
+            expandable.registers()
+        );
+    }
+
+    assert_eq!(
+        expandable.try_message(20),
+        Err(Error::InvalidLength {
+            expected: 19,
+            actual: 20
+        })
+    );
+}
+
+#[test]
+fn test_second_preimage() {
+    let mut target = vec![0; 1000 * ToyHash16::BLOCK_SIZE + 5];
+    thread_rng().fill(&mut target[..]);
+
+    let forged = find_second_preimage::<ToyHash16>(&target);
+    assert_ne!(forged, target);
+    assert_eq!(forged.len(), target.len());
+    assert_eq!(ToyHash16::digest(&forged), ToyHash16::digest(&target));
+
+    assert_eq!(
+        try_find_second_preimage::<ToyHash16>(&[0; 20]),
+        Err(Error::InvalidLength {
+            expected: 32,
+            actual: 20
+        })
+    );
+}
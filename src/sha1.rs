@@ -1,6 +1,7 @@
 use std::mem;
 use std::num::Wrapping;
 
+use crate::hasher::{self, Hasher, MerkleDamgard};
 use crate::padding;
 
 const H0: u32 = 0x67452301;
@@ -82,14 +83,7 @@ impl SHA1 {
             self.process_chunk(&chunk);
         }
 
-        let mut result = Vec::with_capacity(20);
-        result.extend_from_slice(&self.h0.0.to_be_bytes());
-        result.extend_from_slice(&self.h1.0.to_be_bytes());
-        result.extend_from_slice(&self.h2.0.to_be_bytes());
-        result.extend_from_slice(&self.h3.0.to_be_bytes());
-        result.extend_from_slice(&self.h4.0.to_be_bytes());
-
-        result
+        self.registers()
     }
 
     fn process_chunk(&mut self, chunk: &[u8]) {
@@ -155,6 +149,48 @@ impl SHA1 {
     }
 }
 
+impl Hasher for SHA1 {
+    const BLOCK_SIZE: usize = CHUNK_SIZE_BYTES;
+    const OUTPUT_SIZE: usize = 20;
+
+    fn new() -> Self {
+        SHA1::new()
+    }
+
+    fn update(&mut self, message: &[u8]) {
+        SHA1::update(self, message)
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        SHA1::finalize(self)
+    }
+}
+
+impl MerkleDamgard for SHA1 {
+    fn from_registers(registers: &[u8], processed_bits: u64) -> Self {
+        let h = hasher::be_u32_words(registers);
+
+        let mut sha1 = SHA1::with_registers(h[0], h[1], h[2], h[3], h[4]);
+        sha1.set_processed_bits(processed_bits);
+        sha1
+    }
+
+    fn registers(&self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(20);
+        result.extend_from_slice(&self.h0.0.to_be_bytes());
+        result.extend_from_slice(&self.h1.0.to_be_bytes());
+        result.extend_from_slice(&self.h2.0.to_be_bytes());
+        result.extend_from_slice(&self.h3.0.to_be_bytes());
+        result.extend_from_slice(&self.h4.0.to_be_bytes());
+
+        result
+    }
+
+    fn padding(message_len: usize) -> Vec<u8> {
+        padding::md_padding_for_len(message_len)
+    }
+}
+
 pub fn sha1(message: &[u8]) -> Vec<u8> {
     let mut sha1 = SHA1::new();
     sha1.update(&message);
@@ -1,6 +1,7 @@
 use std::mem;
 use std::num::Wrapping;
 
+use crate::hasher::{self, Hasher, MerkleDamgard};
 use crate::padding;
 
 const H0: u32 = 0x6A09E667;
@@ -111,17 +112,7 @@ impl SHA256 {
             self.process_chunk(&chunk);
         }
 
-        let mut result = Vec::with_capacity(32);
-        result.extend_from_slice(&self.h0.0.to_be_bytes());
-        result.extend_from_slice(&self.h1.0.to_be_bytes());
-        result.extend_from_slice(&self.h2.0.to_be_bytes());
-        result.extend_from_slice(&self.h3.0.to_be_bytes());
-        result.extend_from_slice(&self.h4.0.to_be_bytes());
-        result.extend_from_slice(&self.h5.0.to_be_bytes());
-        result.extend_from_slice(&self.h6.0.to_be_bytes());
-        result.extend_from_slice(&self.h7.0.to_be_bytes());
-
-        result
+        self.registers()
     }
 
     fn process_chunk(&mut self, chunk: &[u8]) {
@@ -181,6 +172,51 @@ impl SHA256 {
     }
 }
 
+impl Hasher for SHA256 {
+    const BLOCK_SIZE: usize = CHUNK_SIZE_BYTES;
+    const OUTPUT_SIZE: usize = 32;
+
+    fn new() -> Self {
+        SHA256::new()
+    }
+
+    fn update(&mut self, message: &[u8]) {
+        SHA256::update(self, message)
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        SHA256::finalize(self)
+    }
+}
+
+impl MerkleDamgard for SHA256 {
+    fn from_registers(registers: &[u8], processed_bits: u64) -> Self {
+        let h = hasher::be_u32_words(registers);
+
+        let mut sha256 = SHA256::with_registers(h[0], h[1], h[2], h[3], h[4], h[5], h[6], h[7]);
+        sha256.set_processed_bits(processed_bits);
+        sha256
+    }
+
+    fn registers(&self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(32);
+        result.extend_from_slice(&self.h0.0.to_be_bytes());
+        result.extend_from_slice(&self.h1.0.to_be_bytes());
+        result.extend_from_slice(&self.h2.0.to_be_bytes());
+        result.extend_from_slice(&self.h3.0.to_be_bytes());
+        result.extend_from_slice(&self.h4.0.to_be_bytes());
+        result.extend_from_slice(&self.h5.0.to_be_bytes());
+        result.extend_from_slice(&self.h6.0.to_be_bytes());
+        result.extend_from_slice(&self.h7.0.to_be_bytes());
+
+        result
+    }
+
+    fn padding(message_len: usize) -> Vec<u8> {
+        padding::md_padding_for_len(message_len)
+    }
+}
+
 pub fn sha256(message: &[u8]) -> Vec<u8> {
     let mut sha256 = SHA256::new();
     sha256.update(&message);
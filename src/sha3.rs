@@ -0,0 +1,462 @@
+use std::mem;
+
+use crate::hasher::{Hasher, MerkleDamgard};
+
+const STATE_SIZE_BYTES: usize = 1600 / 8;
+
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+// Rotation offsets for rho, and the lane each one ends up in after pi, in the order pi visits
+// them starting from lane (1, 0)
+const RHO_OFFSETS: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+const PI_LANES: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+// Domain separation suffixes, including the first bit of the pad10*1 padding
+const SHA3_SUFFIX: u8 = 0x06;
+const SHAKE_SUFFIX: u8 = 0x1f;
+
+// The 24-round Keccak permutation, on 5x5 lanes indexed by x + 5y
+pub fn keccak_f1600(state: &mut [u64; 25]) {
+    for &round_constant in ROUND_CONSTANTS.iter() {
+        // Theta
+        let mut columns = [0; 5];
+        for (x, column) in columns.iter_mut().enumerate() {
+            *column = state[x] ^ state[x + 5] ^ state[x + 10] ^ state[x + 15] ^ state[x + 20];
+        }
+
+        for x in 0..5 {
+            let d = columns[(x + 4) % 5] ^ columns[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                state[x + 5 * y] ^= d;
+            }
+        }
+
+        // Rho and pi
+        let mut carried = state[1];
+        for (&offset, &lane) in RHO_OFFSETS.iter().zip(PI_LANES.iter()) {
+            let next = state[lane];
+            state[lane] = carried.rotate_left(offset);
+            carried = next;
+        }
+
+        // Chi
+        for y in 0..5 {
+            let row = [
+                state[5 * y],
+                state[5 * y + 1],
+                state[5 * y + 2],
+                state[5 * y + 3],
+                state[5 * y + 4],
+            ];
+
+            for x in 0..5 {
+                state[x + 5 * y] = row[x] ^ (!row[(x + 1) % 5] & row[(x + 2) % 5]);
+            }
+        }
+
+        // Iota
+        state[0] ^= round_constant;
+    }
+}
+
+// A Keccak sponge: input is absorbed `rate` bytes at a time, and the remaining capacity is never
+// output directly. That hidden part of the state is what defeats length extension, since a digest
+// doesn't have enough of the state to carry on from
+pub struct Sponge {
+    state: [u64; 25],
+    rate: usize,
+    suffix: u8,
+
+    incomplete_block: Vec<u8>,
+    squeeze_position: Option<usize>,
+}
+
+impl Sponge {
+    // `suffix` holds the domain separation bits, followed by a one bit that starts the padding
+    pub fn new(rate: usize, suffix: u8) -> Self {
+        if rate == 0 || rate >= STATE_SIZE_BYTES || !rate.is_multiple_of(8) {
+            panic!("Invalid sponge rate: {} bytes", rate);
+        }
+
+        Self {
+            state: [0; 25],
+            rate,
+            suffix,
+            incomplete_block: Vec::with_capacity(rate),
+            squeeze_position: None,
+        }
+    }
+
+    pub fn with_capacity(capacity: usize, suffix: u8) -> Self {
+        Self::new(STATE_SIZE_BYTES - capacity, suffix)
+    }
+
+    pub fn rate(&self) -> usize {
+        self.rate
+    }
+
+    pub fn capacity(&self) -> usize {
+        STATE_SIZE_BYTES - self.rate
+    }
+
+    pub fn absorb(&mut self, mut message: &[u8]) {
+        if self.squeeze_position.is_some() {
+            panic!("Can't absorb into a sponge that's already being squeezed");
+        }
+
+        if self.incomplete_block.len() + message.len() < self.rate {
+            self.incomplete_block.extend_from_slice(message);
+            return;
+        }
+
+        if !self.incomplete_block.is_empty() {
+            let mut incomplete = mem::take(&mut self.incomplete_block);
+
+            let (left, right) = message.split_at(self.rate - incomplete.len());
+            incomplete.extend_from_slice(left);
+
+            self.absorb_block(&incomplete);
+            message = right;
+        }
+
+        let mut blocks = message.chunks_exact(self.rate);
+        for block in &mut blocks {
+            self.absorb_block(block);
+        }
+
+        self.incomplete_block.extend_from_slice(blocks.remainder());
+    }
+
+    pub fn squeeze(&mut self, output: &mut [u8]) {
+        let mut position = match self.squeeze_position {
+            Some(position) => position,
+
+            None => {
+                let padding = pad(self.suffix, self.rate, self.incomplete_block.len());
+                let mut last_block = mem::take(&mut self.incomplete_block);
+                last_block.extend(padding);
+                self.absorb_block(&last_block);
+
+                0
+            }
+        };
+
+        for byte in output.iter_mut() {
+            if position == self.rate {
+                keccak_f1600(&mut self.state);
+                position = 0;
+            }
+
+            *byte = self.state_byte(position);
+            position += 1;
+        }
+
+        self.squeeze_position = Some(position);
+    }
+
+    fn absorb_block(&mut self, block: &[u8]) {
+        for (lane, word) in self.state.iter_mut().zip(block.chunks(8)) {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(word);
+            *lane ^= u64::from_le_bytes(bytes);
+        }
+
+        keccak_f1600(&mut self.state);
+    }
+
+    fn state_byte(&self, index: usize) -> u8 {
+        self.state[index / 8].to_le_bytes()[index % 8]
+    }
+
+    fn state_bytes(&self) -> Vec<u8> {
+        self.state
+            .iter()
+            .flat_map(|lane| lane.to_le_bytes())
+            .collect()
+    }
+}
+
+// pad10*1, after the suffix bits. A single byte of padding holds both ends
+fn pad(suffix: u8, rate: usize, message_len: usize) -> Vec<u8> {
+    let mut padding = vec![0; rate - message_len % rate];
+    padding[0] = suffix;
+
+    let last = padding.len() - 1;
+    padding[last] |= 0x80;
+    padding
+}
+
+macro_rules! sha3_variant {
+    ($name:ident, $function:ident, $output_size:expr) => {
+        #[allow(non_camel_case_types)]
+        pub struct $name {
+            sponge: Sponge,
+        }
+
+        impl $name {
+            pub fn new() -> Self {
+                Self {
+                    sponge: Sponge::with_capacity(2 * $output_size, SHA3_SUFFIX),
+                }
+            }
+
+            pub fn update(&mut self, message: &[u8]) {
+                self.sponge.absorb(message)
+            }
+
+            pub fn finalize(mut self) -> Vec<u8> {
+                let mut digest = vec![0; $output_size];
+                self.sponge.squeeze(&mut digest);
+                digest
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl Hasher for $name {
+            const BLOCK_SIZE: usize = STATE_SIZE_BYTES - 2 * $output_size;
+            const OUTPUT_SIZE: usize = $output_size;
+
+            fn new() -> Self {
+                $name::new()
+            }
+
+            fn update(&mut self, message: &[u8]) {
+                $name::update(self, message)
+            }
+
+            fn finalize(self) -> Vec<u8> {
+                $name::finalize(self)
+            }
+        }
+
+        // Not Merkle–Damgård, but the permutation chains blocks the same way. The registers are the
+        // entire 1600-bit state, capacity included, so a digest is nowhere near enough to resume
+        // from
+        impl MerkleDamgard for $name {
+            fn from_registers(registers: &[u8], _processed_bits: u64) -> Self {
+                assert_eq!(registers.len(), STATE_SIZE_BYTES);
+
+                let mut hasher = $name::new();
+                for (lane, bytes) in hasher.sponge.state.iter_mut().zip(registers.chunks(8)) {
+                    let mut lane_bytes = [0; 8];
+                    lane_bytes.copy_from_slice(bytes);
+                    *lane = u64::from_le_bytes(lane_bytes);
+                }
+
+                hasher
+            }
+
+            fn registers(&self) -> Vec<u8> {
+                self.sponge.state_bytes()
+            }
+
+            fn padding(message_len: usize) -> Vec<u8> {
+                pad(SHA3_SUFFIX, Self::BLOCK_SIZE, message_len)
+            }
+        }
+
+        pub fn $function(message: &[u8]) -> Vec<u8> {
+            let mut hasher = $name::new();
+            hasher.update(message);
+            hasher.finalize()
+        }
+    };
+}
+
+sha3_variant!(SHA3_224, sha3_224, 28);
+sha3_variant!(SHA3_256, sha3_256, 32);
+sha3_variant!(SHA3_384, sha3_384, 48);
+sha3_variant!(SHA3_512, sha3_512, 64);
+
+// Extendable-output functions: any amount of output, read with as many `squeeze` calls as needed
+macro_rules! shake_variant {
+    ($name:ident, $function:ident, $security_bits:expr) => {
+        pub struct $name {
+            sponge: Sponge,
+        }
+
+        impl $name {
+            pub fn new() -> Self {
+                Self {
+                    sponge: Sponge::with_capacity(2 * $security_bits / 8, SHAKE_SUFFIX),
+                }
+            }
+
+            pub fn update(&mut self, message: &[u8]) {
+                self.sponge.absorb(message)
+            }
+
+            pub fn squeeze(&mut self, output: &mut [u8]) {
+                self.sponge.squeeze(output)
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        pub fn $function(message: &[u8], output_len: usize) -> Vec<u8> {
+            let mut shake = $name::new();
+            shake.update(message);
+
+            let mut output = vec![0; output_len];
+            shake.squeeze(&mut output);
+            output
+        }
+    };
+}
+
+shake_variant!(Shake128, shake128, 128);
+shake_variant!(Shake256, shake256, 256);
+
+#[cfg(test)]
+use crate::encoding::bytes_to_hex;
+
+#[test]
+fn test_sha3_vectors() {
+    assert_eq!(
+        bytes_to_hex(&sha3_224(b"abc")),
+        "e642824c3f8cf24ad09234ee7d3c766fc9a3a5168d0c94ad73b46fdf"
+    );
+    assert_eq!(
+        bytes_to_hex(&sha3_256(b"abc")),
+        "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"
+    );
+    assert_eq!(
+        bytes_to_hex(&sha3_384(b"abc")),
+        "ec01498288516fc926459f58e2c6ad8df9b473cb0fc08c2596da7cf0e49be4b2\
+         98d88cea927ac7f539f1edf228376d25"
+    );
+    assert_eq!(
+        bytes_to_hex(&sha3_512(b"abc")),
+        "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e\
+         10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0"
+    );
+    assert_eq!(
+        bytes_to_hex(&sha3_256(&[])),
+        "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a"
+    );
+
+    // Several blocks, fed in pieces that don't line up with them
+    let message: Vec<u8> = (0..=255).chain(0..=255).collect();
+    let mut sha3 = SHA3_256::new();
+    for chunk in message.chunks(100) {
+        sha3.update(chunk);
+    }
+    assert_eq!(
+        bytes_to_hex(&sha3.finalize()),
+        "d4728ea5e9f3819f2b4760151a8f802dbe9f941fd6fb59b3715892436555772a"
+    );
+}
+
+#[test]
+fn test_shake_vectors() {
+    assert_eq!(
+        bytes_to_hex(&shake128(b"", 32)),
+        "7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26"
+    );
+    assert_eq!(
+        bytes_to_hex(&shake256(b"abc", 64)),
+        "483366601360a8771c6863080cc4114d8db44530f8f1e1ee4f94ea37e78b5739\
+         d5a15bef186a5386c75744c0527e1faa9f8726e462a12a4feb06bd8801e751e4"
+    );
+
+    // Squeezing past the rate, a few bytes at a time
+    let expected = shake128(b"abc", 200);
+    assert!(bytes_to_hex(&expected).ends_with("6aa5b4cd"));
+
+    let mut shake = Shake128::new();
+    shake.update(b"abc");
+    let mut output = vec![0; 200];
+    for chunk in output.chunks_mut(7) {
+        shake.squeeze(chunk);
+    }
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn test_hmac_sha3() {
+    use crate::hmac::hmac;
+
+    assert_eq!(
+        bytes_to_hex(&hmac::<SHA3_256>(b"Jefe", b"what do ya want for nothing?")),
+        "c7d4072e788877ae3596bbb0da73b887c9171f93095b294ae857fbe2645e1ba5"
+    );
+}
+
+// The challenge 29/30 attack, pointed at SHA-3: resume from the MAC, pretend the padding was part
+// of the message, and append. It works with the full state, but the MAC only has the first 32
+// of its 200 bytes
+#[test]
+fn test_sha3_resists_length_extension() {
+    let secret = b"YELLOW SUBMARINE";
+    let message = b"comment1=cooking%20MCs;userdata=foo";
+    let suffix = b";admin=true";
+
+    let mut keyed = secret.to_vec();
+    keyed.extend_from_slice(message);
+
+    let mut forged_message = message.to_vec();
+    forged_message.extend(SHA3_256::padding(keyed.len()));
+    forged_message.extend_from_slice(suffix);
+
+    let mut forged_keyed = secret.to_vec();
+    forged_keyed.extend_from_slice(&forged_message);
+    let expected_mac = sha3_256(&forged_keyed);
+
+    let extend_from = |registers: &[u8]| {
+        let mut sha3 = SHA3_256::from_registers(registers, 0);
+        sha3.update(suffix);
+        sha3.finalize()
+    };
+
+    // Stopping right before the padding would be applied gives the full state
+    let mut padded = keyed.clone();
+    padded.extend(SHA3_256::padding(keyed.len()));
+    let mut hidden_state = SHA3_256::new();
+    hidden_state.update(&padded);
+    assert_eq!(extend_from(&hidden_state.registers()), expected_mac);
+
+    let mac = sha3_256(&keyed);
+    assert_eq!(&hidden_state.registers()[..32], &mac[..]);
+
+    let mut guessed_state = mac;
+    guessed_state.resize(STATE_SIZE_BYTES, 0);
+    assert_ne!(extend_from(&guessed_state), expected_mac);
+}
@@ -0,0 +1,358 @@
+use std::mem;
+use std::num::Wrapping;
+
+use crate::hasher::{self, Hasher, MerkleDamgard};
+use crate::padding::{self, LengthEncoding};
+
+const SHA512_H: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+const SHA384_H: [u64; 8] = [
+    0xcbbb9d5dc1059ed8,
+    0x629a292a367cd507,
+    0x9159015a3070dd17,
+    0x152fecd8f70e5939,
+    0x67332667ffc00b31,
+    0x8eb44a8768581511,
+    0xdb0c2e0d64f98fa7,
+    0x47b5481dbefa4fa4,
+];
+
+// From the SHA-512/t IV generation function in FIPS 180-4, with t = 256
+const SHA512_256_H: [u64; 8] = [
+    0x22312194fc2bf72c,
+    0x9f555fa3c84c64c2,
+    0x2393b86b6f53b151,
+    0x963877195940eabd,
+    0x96283ee2a88effe3,
+    0xbe5e1e2553863992,
+    0x2b0199fc2c85b8aa,
+    0x0eb72ddc81c52ca2,
+];
+
+#[rustfmt::skip]
+const K: &[u64] = &[
+    0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+    0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+    0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+    0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+    0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+    0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+    0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+    0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+    0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+    0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+    0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+    0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+    0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
+];
+
+const CHUNK_SIZE_BYTES: usize = 1024 / 8;
+
+// The compression function shared by SHA-512 and its truncated variants, which only differ in
+// their initial registers and in how much of the final state they output
+struct State {
+    h: [Wrapping<u64>; 8],
+
+    w_buffer: Vec<Wrapping<u64>>,
+    incomplete_chunk: Vec<u8>,
+    processed_bits: u128,
+}
+
+impl State {
+    fn with_registers(registers: [u64; 8]) -> Self {
+        Self {
+            h: registers.map(Wrapping),
+            w_buffer: Vec::with_capacity(80),
+            incomplete_chunk: Vec::with_capacity(CHUNK_SIZE_BYTES),
+            processed_bits: 0,
+        }
+    }
+
+    fn update(&mut self, mut message: &[u8]) {
+        if self.incomplete_chunk.len() + message.len() < CHUNK_SIZE_BYTES {
+            self.incomplete_chunk.extend_from_slice(message);
+            return;
+        }
+
+        if !self.incomplete_chunk.is_empty() {
+            let mut incomplete = mem::replace(
+                &mut self.incomplete_chunk,
+                Vec::with_capacity(CHUNK_SIZE_BYTES),
+            );
+
+            let take_from_message = CHUNK_SIZE_BYTES - incomplete.len();
+            let (left, right) = message.split_at(take_from_message);
+
+            incomplete.extend_from_slice(left);
+
+            self.process_chunk(&incomplete);
+            message = right;
+        }
+
+        for chunk in message.chunks(CHUNK_SIZE_BYTES) {
+            if chunk.len() == CHUNK_SIZE_BYTES {
+                self.process_chunk(chunk);
+            } else {
+                self.incomplete_chunk.extend_from_slice(chunk);
+                return;
+            }
+        }
+    }
+
+    fn finalize(mut self, output_size: usize) -> Vec<u8> {
+        let final_chunks = padding::md_padding_with(
+            &self.incomplete_chunk,
+            self.processed_bits,
+            CHUNK_SIZE_BYTES,
+            LengthEncoding::BigEndian128,
+        );
+        for chunk in final_chunks.chunks(CHUNK_SIZE_BYTES) {
+            self.process_chunk(chunk);
+        }
+
+        let mut result = self.registers();
+        result.truncate(output_size);
+        result
+    }
+
+    fn registers(&self) -> Vec<u8> {
+        self.h.iter().flat_map(|h| h.0.to_be_bytes()).collect()
+    }
+
+    fn process_chunk(&mut self, chunk: &[u8]) {
+        assert_eq!(chunk.len(), CHUNK_SIZE_BYTES);
+
+        let w = &mut self.w_buffer;
+
+        w.clear();
+        w.extend(hasher::be_u64_words(chunk).into_iter().map(Wrapping));
+        w.resize(80, Wrapping(0));
+
+        for i in 16..80 {
+            let s0 = w[i - 15].0.rotate_right(1) ^ w[i - 15].0.rotate_right(8) ^ (w[i - 15].0 >> 7);
+            let s1 = w[i - 2].0.rotate_right(19) ^ w[i - 2].0.rotate_right(61) ^ (w[i - 2].0 >> 6);
+
+            w[i] = w[i - 16] + Wrapping(s0) + w[i - 7] + Wrapping(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.h;
+
+        for i in 0..80 {
+            let s1 = Wrapping(e.0.rotate_right(14) ^ e.0.rotate_right(18) ^ e.0.rotate_right(41));
+            let ch = (e & f) ^ ((!e) & g);
+            let temp1 = h + s1 + ch + Wrapping(K[i]) + w[i];
+
+            let s0 = Wrapping(a.0.rotate_right(28) ^ a.0.rotate_right(34) ^ a.0.rotate_right(39));
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0 + maj;
+
+            h = g;
+            g = f;
+            f = e;
+            e = d + temp1;
+            d = c;
+            c = b;
+            b = a;
+            a = temp1 + temp2;
+        }
+
+        for (register, value) in self.h.iter_mut().zip(&[a, b, c, d, e, f, g, h]) {
+            *register += value;
+        }
+
+        self.processed_bits += (chunk.len() * 8) as u128;
+    }
+}
+
+macro_rules! sha512_variant {
+    ($(#[$attr:meta])* $name:ident, $function:ident, $initial_registers:expr, $output_size:expr) => {
+        $(#[$attr])*
+        pub struct $name {
+            state: State,
+        }
+
+        impl $name {
+            pub fn new() -> Self {
+                Self::with_registers($initial_registers)
+            }
+
+            pub fn with_registers(registers: [u64; 8]) -> Self {
+                Self {
+                    state: State::with_registers(registers),
+                }
+            }
+
+            pub fn set_processed_bits(&mut self, processed_bits: u128) {
+                self.state.processed_bits = processed_bits;
+            }
+
+            pub fn update(&mut self, message: &[u8]) {
+                self.state.update(message)
+            }
+
+            pub fn finalize(self) -> Vec<u8> {
+                self.state.finalize($output_size)
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl Hasher for $name {
+            const BLOCK_SIZE: usize = CHUNK_SIZE_BYTES;
+            const OUTPUT_SIZE: usize = $output_size;
+
+            fn new() -> Self {
+                $name::new()
+            }
+
+            fn update(&mut self, message: &[u8]) {
+                $name::update(self, message)
+            }
+
+            fn finalize(self) -> Vec<u8> {
+                $name::finalize(self)
+            }
+        }
+
+        impl MerkleDamgard for $name {
+            fn from_registers(registers: &[u8], processed_bits: u64) -> Self {
+                let mut h = [0; 8];
+                h.copy_from_slice(&hasher::be_u64_words(registers));
+
+                let mut hasher = $name::with_registers(h);
+                hasher.set_processed_bits(processed_bits as u128);
+                hasher
+            }
+
+            // Always the full 512-bit state, even for the truncated variants
+            fn registers(&self) -> Vec<u8> {
+                self.state.registers()
+            }
+
+            fn padding(message_len: usize) -> Vec<u8> {
+                padding::md_padding_for_len_with(
+                    message_len,
+                    CHUNK_SIZE_BYTES,
+                    LengthEncoding::BigEndian128,
+                )
+            }
+        }
+
+        pub fn $function(message: &[u8]) -> Vec<u8> {
+            let mut hasher = $name::new();
+            hasher.update(message);
+            hasher.finalize()
+        }
+    };
+}
+
+sha512_variant!(SHA512, sha512, SHA512_H, 64);
+sha512_variant!(SHA384, sha384, SHA384_H, 48);
+sha512_variant!(
+    #[allow(non_camel_case_types)]
+    SHA512_256,
+    sha512_256,
+    SHA512_256_H,
+    32
+);
+
+#[cfg(test)]
+use crate::encoding::bytes_to_hex;
+
+#[cfg(test)]
+const TWO_BLOCK_MESSAGE: &[u8] = b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmn\
+                                    hijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu";
+
+#[test]
+fn test_sha512_fips_180_4_vectors() {
+    assert_eq!(
+        bytes_to_hex(&sha512(b"abc")),
+        "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+         2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+    );
+    assert_eq!(
+        bytes_to_hex(&sha512(TWO_BLOCK_MESSAGE)),
+        "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018\
+         501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909"
+    );
+    assert_eq!(
+        bytes_to_hex(&sha512(&[])),
+        "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce\
+         47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e"
+    );
+}
+
+#[test]
+fn test_sha384_fips_180_4_vectors() {
+    assert_eq!(
+        bytes_to_hex(&sha384(b"abc")),
+        "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed\
+         8086072ba1e7cc2358baeca134c825a7"
+    );
+    assert_eq!(
+        bytes_to_hex(&sha384(TWO_BLOCK_MESSAGE)),
+        "09330c33f71147e83d192fc782cd1b4753111b173b3b05d22fa08086e3b0f712\
+         fcc7c71a557e2db966c3e9fa91746039"
+    );
+}
+
+#[test]
+fn test_sha512_256_fips_180_4_vectors() {
+    assert_eq!(
+        bytes_to_hex(&sha512_256(b"abc")),
+        "53048e2681941ef99b2e29b76b4c7dabe4c2d0c634fc6d46e0e2f13107e7af23"
+    );
+    assert_eq!(
+        bytes_to_hex(&sha512_256(TWO_BLOCK_MESSAGE)),
+        "3928e184fb8690f840da3988121d31be65cb9d3ef83ee6146feac861e19b563a"
+    );
+}
+
+#[test]
+fn test_sha512_incremental() {
+    let mut sha512 = SHA512::new();
+    for chunk in TWO_BLOCK_MESSAGE.chunks(5) {
+        sha512.update(chunk);
+    }
+
+    assert_eq!(sha512.finalize(), self::sha512(TWO_BLOCK_MESSAGE));
+}
+
+#[test]
+fn test_hmac_sha512_and_sha384() {
+    use crate::hmac::hmac;
+
+    // RFC 4231 test case 2
+    let message = b"what do ya want for nothing?";
+
+    assert_eq!(
+        bytes_to_hex(&hmac::<SHA512>(b"Jefe", message)),
+        "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554\
+         9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+    );
+    assert_eq!(
+        bytes_to_hex(&hmac::<SHA384>(b"Jefe", message)),
+        "af45d2e376484031617f78d2b58a6b1b9c7ef464f5a01b47e42ec3736322445e\
+         8e2240ca5e69e2c78b3239ecfab21649"
+    );
+}
@@ -5,7 +5,7 @@ use rug::{ops::SubFrom, Integer};
 
 use crate::{
     bignum,
-    hmac::hmac_sha256,
+    hmac::{hmac_sha256, HmacSha256},
     sha256::{sha256, SHA256},
 };
 
@@ -65,9 +65,10 @@ impl Server {
 
     pub fn verify_session_hmac(&self, client_pub: &Integer, client_hmac: &[u8]) -> bool {
         let session_key = self.compute_session_key(&client_pub);
-        let computed_hmac = hmac_sha256(&session_key, &self.verifier.salt);
+        let mut hmac = HmacSha256::new(&session_key);
+        hmac.update(&self.verifier.salt);
 
-        client_hmac == computed_hmac.as_slice()
+        hmac.verify(client_hmac)
     }
 
     fn compute_session_key(&self, client_pub: &Integer) -> Vec<u8> {
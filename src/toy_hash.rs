@@ -0,0 +1,152 @@
+use std::mem;
+
+use crate::aes::Aes128;
+use crate::block_cipher::BlockCipher;
+use crate::hasher::{Hasher, MerkleDamgard};
+use crate::padding::{self, LengthEncoding};
+
+const BLOCK_SIZE: usize = 16;
+
+// Deliberately weak Merkle–Damgård hashes, for attacks that need to find generic collisions in
+// reasonable time. The compression function encrypts the message block with AES-128, keyed by the
+// chaining value (zero-padded to a full key), and truncates the result back down to the size of
+// the chaining value. Padding is the usual one bit, zeros and a 64-bit big-endian length, just on
+// 16-byte blocks
+macro_rules! toy_hash_variant {
+    ($(#[$attr:meta])* $name:ident, $state_size:expr, $iv:expr) => {
+        $(#[$attr])*
+        pub struct $name {
+            state: Vec<u8>,
+            processed_bits: u64,
+            incomplete_block: Vec<u8>,
+        }
+
+        impl Hasher for $name {
+            const BLOCK_SIZE: usize = BLOCK_SIZE;
+            const OUTPUT_SIZE: usize = $state_size;
+
+            fn new() -> Self {
+                Self::from_registers(&$iv, 0)
+            }
+
+            fn update(&mut self, mut message: &[u8]) {
+                if self.incomplete_block.len() + message.len() < BLOCK_SIZE {
+                    self.incomplete_block.extend_from_slice(message);
+                    return;
+                }
+
+                if !self.incomplete_block.is_empty() {
+                    let (left, right) = message.split_at(BLOCK_SIZE - self.incomplete_block.len());
+                    let mut block = mem::take(&mut self.incomplete_block);
+                    block.extend_from_slice(left);
+
+                    self.process_block(&block);
+                    message = right;
+                }
+
+                let mut blocks = message.chunks_exact(BLOCK_SIZE);
+                for block in &mut blocks {
+                    self.process_block(block);
+                }
+
+                self.incomplete_block.extend_from_slice(blocks.remainder());
+            }
+
+            fn finalize(mut self) -> Vec<u8> {
+                let final_blocks = padding::md_padding_with(
+                    &self.incomplete_block,
+                    self.processed_bits as u128,
+                    BLOCK_SIZE,
+                    LengthEncoding::BigEndian64,
+                );
+
+                for block in final_blocks.chunks(BLOCK_SIZE) {
+                    self.process_block(block);
+                }
+
+                self.state
+            }
+        }
+
+        impl MerkleDamgard for $name {
+            fn from_registers(registers: &[u8], processed_bits: u64) -> Self {
+                assert_eq!(registers.len(), $state_size);
+
+                Self {
+                    state: registers.to_vec(),
+                    processed_bits,
+                    incomplete_block: Vec::with_capacity(BLOCK_SIZE),
+                }
+            }
+
+            fn registers(&self) -> Vec<u8> {
+                self.state.clone()
+            }
+
+            fn padding(message_len: usize) -> Vec<u8> {
+                padding::md_padding_for_len_with(
+                    message_len,
+                    BLOCK_SIZE,
+                    LengthEncoding::BigEndian64,
+                )
+            }
+
+            fn compress(registers: &[u8], block: &[u8]) -> Vec<u8> {
+                compress(registers, block)
+            }
+        }
+
+        impl $name {
+            fn process_block(&mut self, block: &[u8]) {
+                self.state = compress(&self.state, block);
+                self.processed_bits += (BLOCK_SIZE * 8) as u64;
+            }
+        }
+    };
+}
+
+toy_hash_variant!(ToyHash16, 2, [0x5a, 0x17]);
+toy_hash_variant!(ToyHash24, 3, [0x5a, 0x17, 0xc3]);
+toy_hash_variant!(ToyHash32, 4, [0x5a, 0x17, 0xc3, 0x8e]);
+
+fn compress(state: &[u8], block: &[u8]) -> Vec<u8> {
+    assert_eq!(block.len(), BLOCK_SIZE);
+
+    let mut key = [0; 16];
+    key[..state.len()].copy_from_slice(state);
+
+    let mut block = block.to_vec();
+    Aes128::new(&key).encrypt_block(&mut block);
+
+    block.truncate(state.len());
+    block
+}
+
+#[cfg(test)]
+use crate::aes::aes_128_ecb_encrypt;
+
+#[test]
+fn test_toy_hash_is_truncated_aes() {
+    let message = b"YELLOW SUBMARINE";
+
+    let mut key = vec![0; 16];
+    key[..2].copy_from_slice(&[0x5a, 0x17]);
+    let first = aes_128_ecb_encrypt(message, &key, false);
+
+    key[..2].copy_from_slice(&first[..2]);
+    let padding_block = ToyHash16::padding(message.len());
+    assert_eq!(padding_block.len(), 16);
+    let second = aes_128_ecb_encrypt(&padding_block, &key, false);
+
+    assert_eq!(ToyHash16::digest(message), &second[..2]);
+
+    let mut hasher = ToyHash32::new();
+    for byte in b"The quick brown fox jumps over the lazy dog".chunks(5) {
+        hasher.update(byte);
+    }
+    assert_eq!(
+        hasher.finalize(),
+        ToyHash32::digest(b"The quick brown fox jumps over the lazy dog")
+    );
+    assert_eq!(ToyHash24::digest(b"").len(), 3);
+}
@@ -1,23 +1,39 @@
+use crate::error::{or_panic, Error, Result};
+
 pub fn fixed_xor(bytes1: &[u8], bytes2: &[u8]) -> Vec<u8> {
-    let len = bytes1.len();
-
-    if bytes2.len() != len {
-        panic!(
-            "fixed_xor: Byte slices don't have same length; {} vs {}",
-            bytes1.len(),
-            bytes2.len()
-        );
-    }
+    try_fixed_xor(bytes1, bytes2).unwrap_or_else(|err| panic!("fixed_xor: {}", err))
+}
 
-    let mut result = Vec::with_capacity(len);
-    for index in 0..len {
-        result.push(bytes1[index] ^ bytes2[index]);
+pub fn try_fixed_xor(bytes1: &[u8], bytes2: &[u8]) -> Result<Vec<u8>> {
+    if bytes1.len() != bytes2.len() {
+        return Err(Error::LengthMismatch {
+            left: bytes1.len(),
+            right: bytes2.len(),
+        });
     }
 
-    result
+    Ok(bytes1.iter().zip(bytes2).map(|(a, b)| a ^ b).collect())
+}
+
+// XORs `other` into `target`, stopping at the end of the shorter one
+pub fn xor_in_place(target: &mut [u8], other: &[u8]) {
+    for (byte, other_byte) in target.iter_mut().zip(other) {
+        *byte ^= other_byte;
+    }
 }
 
 pub fn rotating_xor(bytes: &[u8], key: &[u8]) -> Vec<u8> {
+    or_panic(try_rotating_xor(bytes, key))
+}
+
+pub fn try_rotating_xor(bytes: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    if key.is_empty() {
+        return Err(Error::InvalidLength {
+            expected: 1,
+            actual: 0,
+        });
+    }
+
     let mut result = bytes.to_vec();
     let key_len = key.len();
 
@@ -25,7 +41,7 @@ pub fn rotating_xor(bytes: &[u8], key: &[u8]) -> Vec<u8> {
         *byte ^= key[index % key_len];
     }
 
-    result
+    Ok(result)
 }
 
 pub fn xor_with_byte(bytes: &[u8], key: u8) -> Vec<u8> {
@@ -37,3 +53,15 @@ pub fn xor_with_byte(bytes: &[u8], key: u8) -> Vec<u8> {
 
     result
 }
+
+#[test]
+fn test_rotating_xor() {
+    assert_eq!(rotating_xor(b"abcde", &[1, 2]), b"``bfd");
+    assert_eq!(
+        try_rotating_xor(b"abc", &[]),
+        Err(Error::InvalidLength {
+            expected: 1,
+            actual: 0
+        })
+    );
+}